@@ -1,24 +1,49 @@
+use std::time::Duration;
+
+use badam_sat_protocol::{JoinPayload, JoinSuccess, Me, NewRoomResponse, Role};
 use futures_util::FutureExt;
 use gloo_net::http::Request;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
 use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
 use web_sys::{Element, HtmlInputElement};
-use yew::{html, Component};
+use yew::{html, platform::time::sleep, Component};
 
 use super::{player::Player, playing_area::PlayingArea};
 
 pub struct App {
     room_id: Option<Uuid>,
     token: String,
+    player_id: usize,
+    spectating: bool,
+    copied: bool,
 }
 
 pub enum Msg {
-    CreateRoom { players: usize, decks: usize },
-    RoomCreated(Uuid),
-    JoinRoom(String),
-    JoinedRoom(Uuid, String),
+    CreateRoom {
+        players: usize,
+        decks: usize,
+        password: Option<String>,
+    },
+    JoinRoom {
+        room_id: String,
+        password: Option<String>,
+    },
+    JoinedRoom {
+        room_id: Uuid,
+        token: String,
+        player_id: usize,
+    },
+    Spectate(Uuid),
+    /// The stored session could not be resumed.
+    SessionExpired,
+    Leave,
+    CopyRoomId,
+    /// Whether the room id made it to the clipboard.
+    Copied(bool),
+    CopyFlashOver,
     Error(String),
 }
 
@@ -26,10 +51,16 @@ impl Component for App {
     type Message = Msg;
     type Properties = ();
 
-    fn create(_ctx: &yew::Context<Self>) -> Self {
+    fn create(ctx: &yew::Context<Self>) -> Self {
+        if let Some(session) = load_session() {
+            ctx.link().send_future(resume(session));
+        }
         Self {
             room_id: None,
             token: String::new(),
+            player_id: 0,
+            spectating: false,
+            copied: false,
         }
     }
 
@@ -38,11 +69,17 @@ impl Component for App {
             html! {
                 <div class="app">
                     <PlayingArea room_id={room_id}/>
-                    <Player room_id={room_id} token={self.token.clone()}/>
+                    if !self.spectating {
+                        <Player room_id={room_id} token={self.token.clone()} player_id={self.player_id}/>
+                    }
                     <details>
                         <summary>{"Room ID"}</summary>
-                        {room_id}
+                        <input type="text" id="room_id_display" readonly=true size=40 value={room_id.to_string()}/>
+                        <button type="button" onclick={ctx.link().callback(|_| Msg::CopyRoomId)}>
+                            { if self.copied { "Copied!" } else { "Copy" } }
+                        </button>
                     </details>
+                    <button type="button" onclick={ctx.link().callback(|_| Msg::Leave)}>{"Leave game"}</button>
                 </div>
             }
         } else {
@@ -58,6 +95,7 @@ impl Component for App {
                 Msg::CreateRoom {
                     players: players_input.value().parse().unwrap(),
                     decks: decks_input.value().parse().unwrap(),
+                    password: password_input(),
                 }
             });
             let join_callback = ctx.link().callback(|_| {
@@ -65,7 +103,10 @@ impl Component for App {
                 let room_id_input = HtmlInputElement::unchecked_from_js(
                     <Element as AsRef<JsValue>>::as_ref(&room_id_element).clone(),
                 );
-                Msg::JoinRoom(room_id_input.value())
+                Msg::JoinRoom {
+                    room_id: room_id_input.value(),
+                    password: password_input(),
+                }
             });
             html! {
                 <div class="app">
@@ -74,6 +115,9 @@ impl Component for App {
                     <br/>
                     <button type="button" onclick={join_callback}>{"Join"}</button>
                     <br/>
+                    <label for="password">{"Password: "}</label>
+                    <input type="password" id="password" placeholder="Only for private rooms"/>
+                    <br/>
                     <label for="players">{"Players: "}</label>
                     <input type="number" id="players" min=2 max=12 placeholder="Number of players"/>
                     <label for="decks">{"Decks: "}</label>
@@ -88,31 +132,60 @@ impl Component for App {
 
     fn update(&mut self, ctx: &yew::Context<Self>, msg: Self::Message) -> bool {
         match msg {
-            Msg::CreateRoom { players, decks } => {
+            Msg::CreateRoom {
+                players,
+                decks,
+                password,
+            } => {
                 ctx.link().send_future({
-                    create_room(players, decks).map(|maybe_payload| match maybe_payload {
-                        Ok(payload) => Msg::RoomCreated(payload.room_id),
+                    create_room(players, decks, password).map(|maybe_payload| match maybe_payload {
+                        Ok(NewRoomResponse {
+                            room_id,
+                            join:
+                                Some(JoinSuccess {
+                                    token: Some(token),
+                                    player_id: Some(player_id),
+                                    ..
+                                }),
+                            ..
+                        }) => Msg::JoinedRoom {
+                            room_id,
+                            token,
+                            player_id,
+                        },
+                        Ok(_) => Msg::Error(AppError::NotJoined.to_string()),
                         Err(err) => Msg::Error(err.to_string()),
                     })
                 });
                 false
             }
-            Msg::RoomCreated(room_id) => {
-                ctx.link().send_message(Msg::JoinRoom(room_id.to_string()));
-                false
-            }
-            Msg::JoinRoom(room_id) => {
+            Msg::JoinRoom { room_id, password } => {
                 match Uuid::try_parse(&room_id) {
                     Ok(room_id) => {
-                        let payload = RoomPayload { room_id };
+                        // watch the game instead if the room is already full
+                        let payload = JoinPayload {
+                            room_id,
+                            player_key: None,
+                            spectate_if_full: true,
+                            password,
+                        };
                         ctx.link().send_future(async move {
                             join_room(payload)
                                 .map(|maybe_join| match maybe_join {
                                     Ok(join_response) => match join_response {
-                                        JoinResponse::Success { _token_type, token } => {
-                                            Msg::JoinedRoom(room_id, token)
+                                        JoinResponse::Success(join) => {
+                                            match (join.role, join.player_id, join.token) {
+                                                (Role::Player, Some(player_id), Some(token)) => {
+                                                    Msg::JoinedRoom {
+                                                        room_id,
+                                                        token,
+                                                        player_id,
+                                                    }
+                                                }
+                                                _ => Msg::Spectate(room_id),
+                                            }
                                         }
-                                        JoinResponse::ClientError(err) => Msg::Error(err),
+                                        JoinResponse::ClientError { error } => Msg::Error(error),
                                     },
                                     Err(err) => Msg::Error(err.to_string()),
                                 })
@@ -125,9 +198,64 @@ impl Component for App {
                 };
                 false
             }
-            Msg::JoinedRoom(room_id, token) => {
+            Msg::JoinedRoom {
+                room_id,
+                token,
+                player_id,
+            } => {
+                store_session(&Session {
+                    room_id,
+                    token: token.clone(),
+                });
                 self.room_id = Some(room_id);
                 self.token = token;
+                self.player_id = player_id;
+                true
+            }
+            Msg::Spectate(room_id) => {
+                self.room_id = Some(room_id);
+                self.spectating = true;
+                true
+            }
+            Msg::SessionExpired => {
+                clear_session();
+                false
+            }
+            Msg::Leave => {
+                clear_session();
+                self.room_id = None;
+                self.token = String::new();
+                self.spectating = false;
+                true
+            }
+            Msg::CopyRoomId => {
+                if let Some(room_id) = self.room_id {
+                    ctx.link()
+                        .send_future(copy_to_clipboard(room_id.to_string()).map(Msg::Copied));
+                }
+                false
+            }
+            Msg::Copied(true) => {
+                self.copied = true;
+                ctx.link().send_future(async {
+                    sleep(Duration::from_secs(2)).await;
+                    Msg::CopyFlashOver
+                });
+                true
+            }
+            Msg::Copied(false) => {
+                // select the room id instead, for the player to copy by hand
+                let element = gloo_utils::document()
+                    .get_element_by_id("room_id_display")
+                    .unwrap();
+                HtmlInputElement::unchecked_from_js(
+                    <Element as AsRef<JsValue>>::as_ref(&element).clone(),
+                )
+                .select();
+                false
+            }
+            Msg::CopyFlashOver => {
+                self.copied = false;
                 true
             }
             Msg::Error(err) => {
@@ -138,41 +266,53 @@ impl Component for App {
     }
 }
 
+/// Read the room password from the lobby form, `None` if left empty.
+fn password_input() -> Option<String> {
+    let element = gloo_utils::document()
+        .get_element_by_id("password")
+        .unwrap();
+    let input =
+        HtmlInputElement::unchecked_from_js(<Element as AsRef<JsValue>>::as_ref(&element).clone());
+    Some(input.value()).filter(|password| !password.is_empty())
+}
+
 #[derive(Debug, thiserror::Error)]
 enum AppError {
     #[error(transparent)]
     GlooError(#[from] gloo_net::Error),
     #[error("not a valid room id")]
     NotARoomId,
-}
-
-#[derive(Debug, Deserialize, Serialize)]
-struct RoomPayload {
-    room_id: Uuid,
+    #[error("could not join the new room")]
+    NotJoined,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 enum JoinResponse {
-    Success {
-        #[serde(rename = "token_type")]
-        _token_type: String,
-        token: String,
-    },
-    ClientError(String),
+    Success(JoinSuccess),
+    ClientError { error: String },
 }
 
-async fn create_room(players: usize, decks: usize) -> Result<RoomPayload, AppError> {
+async fn create_room(
+    players: usize,
+    decks: usize,
+    password: Option<String>,
+) -> Result<NewRoomResponse, AppError> {
     let response = Request::post("/badam_sat/api/create_room")
-        .json(&json!({ "players": players, "decks": decks }))
+        .json(&json!({
+            "players": players,
+            "decks": decks,
+            "join": true,
+            "password": password,
+        }))
         .unwrap()
         .send()
         .await?;
-    let room_payload: RoomPayload = response.json().await?;
-    Ok(room_payload)
+    let new_room: NewRoomResponse = response.json().await?;
+    Ok(new_room)
 }
 
-async fn join_room(payload: RoomPayload) -> Result<JoinResponse, AppError> {
+async fn join_room(payload: JoinPayload) -> Result<JoinResponse, AppError> {
     let response = Request::post("/badam_sat/api/join")
         .json(&payload)
         .unwrap()
@@ -181,3 +321,62 @@ async fn join_room(payload: RoomPayload) -> Result<JoinResponse, AppError> {
     let join_response: JoinResponse = response.json().await?;
     Ok(join_response)
 }
+
+/// Write `text` to the clipboard, returning whether it worked. The clipboard
+/// API is missing on pages that are not served over HTTPS.
+async fn copy_to_clipboard(text: String) -> bool {
+    let clipboard = gloo_utils::window().navigator().clipboard();
+    if clipboard.is_undefined() {
+        return false;
+    }
+    JsFuture::from(clipboard.write_text(&text)).await.is_ok()
+}
+
+/// Key under which the seat of the player is kept in local storage, so that
+/// reloading the page does not lose it.
+const SESSION_KEY: &str = "badam_sat_session";
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Session {
+    room_id: Uuid,
+    token: String,
+}
+
+fn load_session() -> Option<Session> {
+    let storage = gloo_utils::window().local_storage().ok()??;
+    let session = storage.get_item(SESSION_KEY).ok()??;
+    serde_json::from_str(&session).ok()
+}
+
+fn store_session(session: &Session) {
+    if let Ok(Some(storage)) = gloo_utils::window().local_storage() {
+        let _ = storage.set_item(SESSION_KEY, &serde_json::to_string(session).unwrap());
+    }
+}
+
+fn clear_session() {
+    if let Ok(Some(storage)) = gloo_utils::window().local_storage() {
+        let _ = storage.remove_item(SESSION_KEY);
+    }
+}
+
+/// Check that the stored token still holds a seat, in a room that still
+/// exists, and take the seat again.
+async fn resume(session: Session) -> Msg {
+    let response = Request::get("/badam_sat/api/me")
+        .header("Authorization", &format!("Bearer {}", session.token))
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.ok() => match response.json::<Me>().await {
+            Ok(me) => Msg::JoinedRoom {
+                room_id: me.room_id,
+                token: session.token,
+                player_id: me.player_id,
+            },
+            Err(_) => Msg::SessionExpired,
+        },
+        Ok(_) => Msg::SessionExpired,
+        Err(err) => Msg::Error(err.to_string()),
+    }
+}
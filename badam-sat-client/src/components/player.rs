@@ -1,15 +1,21 @@
-use std::{collections::HashMap, time::Duration};
+use std::time::Duration;
 
+use badam_sat_protocol::{Action, MyTurn};
 use card_deck::standard_deck::{Card, Rank, Suit};
 use futures_util::FutureExt;
 use gloo_net::http::Request;
-use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use yew::{html, platform::time::sleep, Component, Html, Properties};
 
 #[derive(Debug, PartialEq)]
 pub struct Player {
-    hand: HashMap<Suit, Vec<Card>>,
+    /// Cards in hand grouped by suit, always in the same suit order so that
+    /// the hand does not reshuffle between polls.
+    hand: Vec<(Suit, Vec<Card>)>,
+    playable: Vec<Card>,
+    is_my_turn: bool,
+    can_pass: bool,
+    current_player: Option<usize>,
 }
 
 impl Default for Player {
@@ -18,21 +24,40 @@ impl Default for Player {
             .into_iter()
             .map(|suit| (suit, Vec::new()))
             .collect();
-        Player { hand }
+        Player {
+            hand,
+            playable: Vec::new(),
+            is_my_turn: false,
+            can_pass: false,
+            current_player: None,
+        }
     }
 }
 
 pub enum Msg {
     QueryHand,
-    Hand(HashMap<Suit, Vec<Card>>),
+    Hand(Player),
     Play(Card),
     Pass,
+    /// The hand and legal moves right after one of our moves went through.
+    Played(Player),
 }
 
 #[derive(Debug, PartialEq, Properties)]
 pub struct Props {
     pub room_id: Uuid,
     pub token: String,
+    pub player_id: usize,
+}
+
+impl Player {
+    /// Take on the hand and legal moves of `turn`, returning whether anything
+    /// changed.
+    fn replace(&mut self, turn: Player) -> bool {
+        let changed = *self != turn;
+        *self = turn;
+        changed
+    }
 }
 
 impl Component for Player {
@@ -45,8 +70,16 @@ impl Component for Player {
     }
 
     fn view(&self, ctx: &yew::Context<Self>) -> yew::Html {
+        let banner = self.current_player.map(|player| {
+            if player == ctx.props().player_id {
+                html! {<div class="turn mine">{"Your turn"}</div>}
+            } else {
+                html! {<div class="turn">{format!("Waiting for Player {player}")}</div>}
+            }
+        });
         html! {
             <>
+                { for banner }
                 <div class="hand">
                 {
                     self.hand.iter().map(|(suit, cards)| html!{
@@ -54,13 +87,15 @@ impl Component for Player {
                             {
                                 cards.iter().map(|card| {
                                     let card = *card;
-                                    html!{<button class="playable" onclick={ctx.link().callback(move |_| Msg::Play(card))}>{card.to_string()}</button>}}).collect::<Html>()
+                                    let legal = self.playable.contains(&card);
+                                    let class = if legal { "playable legal" } else { "playable" };
+                                    html!{<button class={class} disabled={!self.is_my_turn || !legal} onclick={ctx.link().callback(move |_| Msg::Play(card))}>{card.to_string()}</button>}}).collect::<Html>()
                             }
                         </div>
                     }).collect::<Html>()
                 }
                 </div>
-                <button onclick={ctx.link().callback(|_| Msg::Pass)}>{"Pass"}</button>
+                <button disabled={!self.can_pass} onclick={ctx.link().callback(|_| Msg::Pass)}>{"Pass"}</button>
             </>
         }
     }
@@ -75,52 +110,54 @@ impl Component for Player {
                 }
                 false
             }
-            Msg::Hand(hand) => {
-                if self.hand == hand {
-                    let token = ctx.props().token.clone();
-                    ctx.link().send_future(async move {
-                        sleep(Duration::from_secs(5)).await;
-                        query_hand(&token).map(Msg::Hand).await
-                    });
-                    false
-                } else {
-                    self.hand = hand;
-                    true
-                }
+            Msg::Hand(turn) => {
+                // keep polling, the turn moves on while the hand stays the same
+                let token = ctx.props().token.clone();
+                ctx.link().send_future(async move {
+                    sleep(Duration::from_secs(5)).await;
+                    query_hand(&token).map(Msg::Hand).await
+                });
+                self.replace(turn)
             }
             Msg::Play(card) => {
-                {
-                    let token = ctx.props().token.clone();
-                    wasm_bindgen_futures::spawn_local(async move {
-                        play(&token, &Action::Play(card)).await
-                    });
-                    ctx.link().send_message(Msg::QueryHand);
-                }
+                let token = ctx.props().token.clone();
+                ctx.link().send_future(async move {
+                    play(&token, &Action::Play(card)).await;
+                    query_hand(&token).map(Msg::Played).await
+                });
                 false
             }
             Msg::Pass => {
-                {
-                    let token = ctx.props().token.clone();
-                    wasm_bindgen_futures::spawn_local(
-                        async move { play(&token, &Action::Pass).await },
-                    );
-                }
+                let token = ctx.props().token.clone();
+                ctx.link().send_future(async move {
+                    play(&token, &Action::Pass).await;
+                    query_hand(&token).map(Msg::Played).await
+                });
                 false
             }
+            Msg::Played(turn) => self.replace(turn),
         }
     }
 }
 
-async fn query_hand(token: &str) -> HashMap<Suit, Vec<Card>> {
-    let response = Request::get("/badam_sat/api/my_hand")
+/// Fetch the hand, the cards that can be played and whose turn it is in one
+/// request, so that the highlights never lag behind the hand.
+async fn query_hand(token: &str) -> Player {
+    let response = Request::get("/badam_sat/api/my_turn")
         .header("Authorization", &format!("Bearer {token}"))
         .send()
         .await
         .unwrap();
-    let mut cards: Vec<Card> = response.json().await.unwrap();
+    let MyTurn {
+        hand: mut cards,
+        playable,
+        is_my_turn,
+        can_pass,
+        current_player,
+    } = response.json().await.unwrap();
     cards.sort_by(card_comparator);
     cards.reverse();
-    let hand: HashMap<Suit, Vec<Card>> = Suit::all_suits()
+    let hand: Vec<(Suit, Vec<Card>)> = Suit::all_suits()
         .into_iter()
         .map(|suit| {
             (
@@ -133,7 +170,13 @@ async fn query_hand(token: &str) -> HashMap<Suit, Vec<Card>> {
             )
         })
         .collect();
-    hand
+    Player {
+        hand,
+        playable,
+        is_my_turn,
+        can_pass,
+        current_player,
+    }
 }
 
 async fn play(token: &str, action: &Action) {
@@ -144,21 +187,15 @@ async fn play(token: &str, action: &Action) {
         .send()
         .await
     {
-        Ok(response) => {
-            if !response.ok() {
-                gloo_dialogs::alert("Invalid move")
-            }
-        }
+        Ok(response) => match response.status() {
+            200..=299 => (),
+            409 => gloo_dialogs::alert("It is not your turn"),
+            _ => gloo_dialogs::alert("Invalid move"),
+        },
         Err(_) => gloo_dialogs::alert("Server error"),
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub enum Action {
-    Play(Card),
-    Pass,
-}
-
 fn card_comparator(c1: &Card, c2: &Card) -> std::cmp::Ordering {
     match (c1.suit().unwrap(), c2.suit().unwrap()) {
         (s1, s2) if s1 == s2 => match (c1.rank().unwrap(), c2.rank().unwrap()) {
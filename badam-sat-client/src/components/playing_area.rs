@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use badam_sat::games::{CardStack, StackState};
+use badam_sat_protocol::{Action, GameState};
 use card_deck::standard_deck::{Card, Rank, Suit};
 use futures_util::FutureExt;
 use gloo_net::http::Request;
@@ -8,13 +9,13 @@ use serde::Deserialize;
 use uuid::Uuid;
 use yew::{html, platform::time::sleep, Component, Html, Properties};
 
-use super::player::Action;
-
 #[derive(Debug, PartialEq)]
 pub struct PlayingArea {
     card_stacks: Vec<CardStack>,
     glow: Option<Card>,
     card_counts: Vec<usize>,
+    ranking: Vec<usize>,
+    current_player: Option<usize>,
     game_started: bool,
 }
 
@@ -25,6 +26,8 @@ impl Default for PlayingArea {
             card_stacks,
             glow: None,
             card_counts: vec![],
+            ranking: vec![],
+            current_player: None,
             game_started: false,
         }
     }
@@ -59,8 +62,26 @@ impl Component for PlayingArea {
                         self.card_counts
                             .iter()
                             .enumerate()
-                            .map(|(idx, count)| html! {
-                                <div class="card_count">{ format!("Player {idx}: {count}") }</div>
+                            .map(|(idx, count)| {
+                                let class = if self.current_player == Some(idx) {
+                                    "card_count active"
+                                } else {
+                                    "card_count"
+                                };
+                                html! {
+                                    <div class={class}>{ format!("Player {idx}: {count}") }</div>
+                                }
+                            })
+                            .collect::<Html>()
+                    }
+                </div>
+                <div class="ranking">
+                    {
+                        self.ranking
+                            .iter()
+                            .enumerate()
+                            .map(|(place, player)| html! {
+                                <div class="finisher">{ format!("{}. Player {player}", place + 1) }</div>
                             })
                             .collect::<Html>()
                     }
@@ -99,18 +120,23 @@ impl Component for PlayingArea {
                 if !self.game_started && self.card_stacks != stacks {
                     self.game_started = true;
                 }
-                if self.game_started && self.card_counts != state.card_counts {
-                    self.card_counts = state.card_counts;
+                let card_counts = state.card_counts.unwrap_or_default();
+                if self.game_started
+                    && (self.card_counts != card_counts
+                        || self.card_stacks != stacks
+                        || self.current_player != state.current_player
+                        || state.finished)
+                {
+                    self.card_counts = card_counts;
                     self.card_stacks = stacks;
-                    if let Some(idx) = self
-                        .card_counts
-                        .iter()
-                        .enumerate()
-                        .find_map(|(idx, count)| if *count == 0 { Some(idx) } else { None })
-                    {
-                        gloo_dialogs::alert(&format!("Player {idx} won!"));
-                    } else {
+                    self.current_player = state.current_player;
+                    self.ranking = state.ranking;
+                    // players who run out of cards join the ranking, but the
+                    // game may go on for the rest of the places
+                    if !state.finished {
                         ctx.link().send_message(Msg::QueryGameState);
+                    } else if let Some(winner) = self.ranking.first() {
+                        gloo_dialogs::alert(&format!("Player {winner} won!"));
                     }
                     ctx.link().send_message(Msg::QueryLastMove);
                     return true;
@@ -175,12 +201,6 @@ enum LastMoveResponse {
     },
 }
 
-#[derive(Debug, Deserialize)]
-pub struct GameState {
-    playing_area: badam_sat::games::PlayingArea,
-    card_counts: Vec<usize>,
-}
-
 fn stack_to_html(suit: &Suit, stack: &CardStack, glow: Option<&Card>) -> Html {
     match stack.stack_state() {
         StackState::Empty => {
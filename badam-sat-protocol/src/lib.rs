@@ -0,0 +1,121 @@
+//! Types exchanged between the बदाम सात server and its clients.
+
+use badam_sat::games::PlayingArea;
+use card_deck::standard_deck::Card;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Request or query identifying a room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RoomPayload {
+    pub room_id: Uuid,
+}
+
+/// Request to join a room.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JoinPayload {
+    pub room_id: Uuid,
+    /// Identity of the player across rooms, for keeping their statistics.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_key: Option<Uuid>,
+    /// Join as a spectator instead of failing when the room is full.
+    #[serde(default)]
+    pub spectate_if_full: bool,
+    /// Password of the room, if it is private.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// Whether someone in a room plays or only watches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    Player,
+    Spectator,
+}
+
+/// Token issued to a player on joining a room.
+///
+/// Spectators watch through the endpoints that take a room id and need no
+/// token, so they are not issued one.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JoinSuccess {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Seat of the player in the room, `None` for spectators.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub player_id: Option<usize>,
+    #[serde(default)]
+    pub role: Role,
+}
+
+/// Seat and room of the player a token was issued to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Me {
+    pub player_id: usize,
+    pub room_id: Uuid,
+}
+
+/// Response to creating a room.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NewRoomResponse {
+    pub room_id: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_commitment: Option<String>,
+    /// Token of the creator, if they joined the room right away.
+    #[serde(flatten)]
+    pub join: Option<JoinSuccess>,
+}
+
+/// A player's hand along with the cards they can play, fetched together so
+/// that both stay in sync.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MyTurn {
+    pub hand: Vec<Card>,
+    /// Cards in the hand that can be played now, empty when it is not the
+    /// player's turn.
+    pub playable: Vec<Card>,
+    pub is_my_turn: bool,
+    /// Whether the player may pass now, which under forced play is only when
+    /// none of their cards can be played.
+    #[serde(default)]
+    pub can_pass: bool,
+    /// Seat of the player whose turn it is, `None` while the game is not in
+    /// play.
+    #[serde(default)]
+    pub current_player: Option<usize>,
+}
+
+/// An action that a player can take; either play a card or pass their turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Action {
+    Play(Card),
+    Pass,
+}
+
+/// Game state that does not reveal players' cards, so can be communicated with everyone.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct GameState {
+    pub playing_area: PlayingArea,
+    /// Number of cards each player holds, `None` when the room hides them.
+    pub card_counts: Option<Vec<usize>>,
+    /// Number of times each player has passed while they could have played.
+    #[serde(default)]
+    pub pass_counts: Vec<usize>,
+    /// Whether the game is over.
+    #[serde(default)]
+    pub finished: bool,
+    /// Players who are out so far, in the order they went out. Once the game
+    /// is over, this is the full finishing order with the winner first.
+    #[serde(default)]
+    pub ranking: Vec<usize>,
+    /// Player whose turn it is, `None` before the cards are dealt and once
+    /// the game is over.
+    #[serde(default)]
+    pub current_player: Option<usize>,
+    pub ready: Vec<bool>,
+    pub theme: String,
+}
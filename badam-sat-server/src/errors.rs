@@ -1,42 +1,105 @@
 use axum::{http::StatusCode, response::IntoResponse, Json};
+use badam_sat::games::InvalidTransition;
 use serde::Serialize;
 use serde_json::json;
 use tokio::sync::{mpsc, oneshot};
 
-#[derive(Debug, Serialize, thiserror::Error)]
+#[derive(Debug, Clone, Serialize, thiserror::Error)]
 pub enum Error {
-    #[error("attempted move is not valid")]
-    InvalidMove,
+    #[error("attempted move is not valid: {0}")]
+    InvalidMove(InvalidTransition),
+    #[error("it is not your turn")]
+    NotYourTurn,
     #[error("game is not ready to accept moves yet")]
     TooEarly,
+    #[error("only the host can start the game")]
+    NotHost,
+    #[error("all players must join and be ready before starting")]
+    PlayersNotReady,
+    #[error("idempotency key must be a UUID")]
+    InvalidIdempotencyKey,
+    #[error("invalid action payload")]
+    InvalidAction,
     #[error("user not authorized")]
     InvalidToken,
+    #[error("no such theme exists")]
+    InvalidTheme,
+    #[error(
+        "room needs at least one player, a deck with the opening card and, if fair, equal hands"
+    )]
+    InvalidRoomConfig,
+    #[error("every seat must be assigned to a team, and every team must have a player")]
+    InvalidTeams,
     #[error("no such room exists")]
     InvalidRoomId,
+    #[error("host action needs a fresh confirmation from /api/host/confirm")]
+    InvalidConfirmation,
+    #[error("room settings cannot change once another player joins or the game starts")]
+    RoomLocked,
+    #[error("token was issued by another server instance")]
+    WrongInstance,
     #[error("cannot join a full room")]
     RoomFull,
+    #[error("player already has a seat in a game in progress")]
+    AlreadyInGame,
     #[error("no such player exists")]
     InvalidPlayerId,
+    #[error("no such card in hand")]
+    InvalidCard,
     #[error("no space left in the server for another game")]
     ServerFull,
     #[error("no last move found")]
     NoMove,
+    #[error("room was not created with a seed")]
+    NoSeed,
+    #[error("game is not over yet")]
+    GameNotOver,
+    #[error("room hides how many cards the players hold")]
+    CountsHidden,
+    #[error("no game is in progress")]
+    NoActiveRoom,
+    #[error("unknown endpoint")]
+    UnknownEndpoint,
     #[error("game server or room terminated unexpectedly")]
     UnexpectedTermination,
+    #[error("too many requests, try again later")]
+    TooManyRequests,
+    #[error("wrong or missing room password")]
+    WrongPassword,
 }
 
 impl IntoResponse for Error {
     fn into_response(self) -> axum::response::Response {
         let response_code = match self {
-            Error::InvalidMove => StatusCode::BAD_REQUEST,
+            Error::InvalidMove(_) => StatusCode::BAD_REQUEST,
+            Error::NotYourTurn => StatusCode::CONFLICT,
             Error::TooEarly => StatusCode::BAD_REQUEST,
+            Error::NotHost => StatusCode::FORBIDDEN,
+            Error::PlayersNotReady => StatusCode::BAD_REQUEST,
+            Error::InvalidIdempotencyKey => StatusCode::BAD_REQUEST,
+            Error::InvalidAction => StatusCode::BAD_REQUEST,
             Error::InvalidToken => StatusCode::UNAUTHORIZED,
+            Error::InvalidTheme => StatusCode::BAD_REQUEST,
+            Error::InvalidRoomConfig => StatusCode::BAD_REQUEST,
+            Error::InvalidTeams => StatusCode::BAD_REQUEST,
             Error::InvalidRoomId => StatusCode::BAD_REQUEST,
+            Error::InvalidConfirmation => StatusCode::BAD_REQUEST,
+            Error::RoomLocked => StatusCode::CONFLICT,
+            Error::WrongInstance => StatusCode::MISDIRECTED_REQUEST,
             Error::RoomFull => StatusCode::BAD_REQUEST,
+            Error::AlreadyInGame => StatusCode::CONFLICT,
             Error::InvalidPlayerId => StatusCode::BAD_REQUEST,
+            Error::InvalidCard => StatusCode::BAD_REQUEST,
             Error::ServerFull => StatusCode::CONFLICT,
             Error::NoMove => StatusCode::NOT_FOUND,
+            Error::NoSeed => StatusCode::NOT_FOUND,
+            Error::GameNotOver => StatusCode::FORBIDDEN,
+            Error::CountsHidden => StatusCode::FORBIDDEN,
+            Error::NoActiveRoom => StatusCode::NOT_FOUND,
+            Error::UnknownEndpoint => StatusCode::NOT_FOUND,
             Error::UnexpectedTermination => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            Error::WrongPassword => StatusCode::FORBIDDEN,
         };
         (response_code, Json(json!({"error": self.to_string()}))).into_response()
     }
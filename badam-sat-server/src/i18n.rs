@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Languages that card labels are available in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    En,
+    Hi,
+}
+
+/// Display names of the suits and ranks in a [`Locale`].
+///
+/// Suits are keyed by their names as they appear in serialized cards and
+/// ranks by their numeric value, 1 for the ace through 13 for the king.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Labels {
+    suits: BTreeMap<&'static str, &'static str>,
+    ranks: BTreeMap<u8, &'static str>,
+}
+
+const SUITS: [&str; 4] = ["Clubs", "Diamonds", "Hearts", "Spades"];
+
+const EN_SUITS: [&str; 4] = ["Clubs", "Diamonds", "Hearts", "Spades"];
+const EN_RANKS: [&str; 13] = [
+    "Ace", "2", "3", "4", "5", "6", "7", "8", "9", "10", "Jack", "Queen", "King",
+];
+
+const HI_SUITS: [&str; 4] = ["चिड़ी", "ईंट", "पान", "हुकुम"];
+const HI_RANKS: [&str; 13] = [
+    "इक्का",
+    "दुक्की",
+    "तिक्की",
+    "चौका",
+    "पंजा",
+    "छक्का",
+    "सत्ता",
+    "अट्ठा",
+    "नहला",
+    "दहला",
+    "गुलाम",
+    "बेगम",
+    "बादशाह",
+];
+
+impl Locale {
+    /// Get the card labels for this locale.
+    pub fn labels(self) -> Labels {
+        let (suits, ranks) = match self {
+            Locale::En => (EN_SUITS, EN_RANKS),
+            Locale::Hi => (HI_SUITS, HI_RANKS),
+        };
+        Labels {
+            suits: SUITS.into_iter().zip(suits).collect(),
+            ranks: (1..=13).zip(ranks).collect(),
+        }
+    }
+}
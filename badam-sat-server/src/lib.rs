@@ -1,84 +1,394 @@
 use axum::{
     async_trait,
-    extract::FromRequestParts,
+    extract::{ConnectInfo, FromRequest, FromRequestParts},
     headers::{authorization::Bearer, Authorization},
-    http::request::Parts,
+    http::{
+        header::{self, HeaderName, InvalidHeaderValue},
+        request::Parts,
+        HeaderMap, HeaderValue, Method, Request, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
     RequestPartsExt, TypedHeader,
 };
-use std::{path::Path, sync::Arc};
+use std::{
+    collections::HashMap,
+    io,
+    net::{SocketAddr, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
-    routing::{get, post},
+    routing::{any, get, patch, post},
     Json, Router,
 };
 
-use card_deck::standard_deck::Card;
+use badam_sat_protocol::{
+    Action, GameState, JoinPayload, JoinSuccess, Me, MyTurn, NewRoomResponse, Role, RoomPayload,
+};
+use card_deck::standard_deck::{Card, Suit};
 use errors::Error;
+use i18n::{Labels, Locale};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder};
 use pasetors::{
     claims::ClaimsValidationRules,
     keys::{AsymmetricKeyPair, AsymmetricSecretKey},
+    paserk::FormatAsPaserk,
     version4::V4,
 };
-use rooms::{Action, GameState};
+use rate_limit::RateLimiter;
+use rooms::{
+    CompactGameState, Confirmation, GameExport, PlayOutcome, RoomConfig, RoomEvent, TurnOrder,
+    TurnTimer, ValidActions, Winner,
+};
 use serde::{Deserialize, Serialize};
-use server::Server;
+use server::{to_hex, Seat, Server};
+use stats::{PlayerStats, StatsStore};
 use tokio::sync::{mpsc, oneshot};
-use tower_http::services::ServeDir;
+use tower_http::{
+    cors::{AllowOrigin, Any, CorsLayer},
+    services::ServeDir,
+};
 use uuid::Uuid;
 
 mod errors;
+mod i18n;
+mod rate_limit;
+mod render;
 mod rooms;
 mod server;
+mod stats;
+
+/// Version of the format of the documents the server exports or stores on
+/// disk, raised whenever the format changes incompatibly.
+pub const SCHEMA_VERSION: u32 = 1;
 
 #[derive(Clone)]
 struct ServerState {
     // ED25519 key for signing PASETO tokens
     sender: RouterServerMessageSender,
     key_pair: AsymmetricKeyPair<V4>,
+    instance_id: Uuid,
 }
 
 /// Create a router for बदाम सात.
+///
+/// Static frontend files are served from `frontend_path` for any non-API
+/// route. If it is `None`, no static files are served and unknown routes
+/// return 404.
+///
+/// Games still going on `max_game_duration` after the cards were dealt are
+/// ended, with the player holding the fewest cards winning. `None` lets games
+/// run for as long as the players keep them active.
+///
+/// Rooms live in the memory of the router that created them. When several
+/// routers share the same `key_pair` behind a load balancer, each one picks a
+/// random instance id that is embedded in the `instance_id` claim of the
+/// tokens it issues, and is reported by `GET /api/whoami`. The token payload
+/// is readable without the key, so the load balancer can route requests
+/// carrying a token to the instance that issued it. Tokens presented to any
+/// other instance are rejected with `421 Misdirected Request`.
+///
+/// Players who join with a `player_key` have their wins and losses kept
+/// across rooms, readable at `GET /api/stats`. The results are appended to
+/// the file at `stats_path`, or only kept in memory if it is `None`.
 pub fn badam_sat_router<P: AsRef<Path>>(
     key_pair: AsymmetricKeyPair<V4>,
     max_rooms: usize,
-    frontend_path: P,
+    max_game_duration: Option<Duration>,
+    frontend_path: Option<P>,
+    stats_path: Option<PathBuf>,
 ) -> Router {
     let (sender, receiver) = mpsc::channel(100);
-    Server::spawn(max_rooms, receiver);
+    let instance_id = Uuid::new_v4();
+    Server::spawn(
+        max_rooms,
+        max_game_duration,
+        instance_id,
+        StatsStore::open(stats_path),
+        receiver,
+    );
     let sender = Arc::new(sender);
-    let state = ServerState { sender, key_pair };
+    let state = ServerState {
+        sender,
+        key_pair,
+        instance_id,
+    };
 
-    let serve_dir = ServeDir::new(frontend_path);
-    Router::new()
+    let router = Router::new()
         .route("/api/create_room", post(create_room))
         .route("/api/join", post(join))
+        .route("/api/quickplay", post(quickplay))
+        .route("/api/ready", post(ready))
+        .route("/api/start", post(start))
+        .route("/api/host/confirm", get(host_confirmation))
+        .route("/api/kick", post(kick))
+        .route("/api/room", patch(reconfigure))
         .route("/api/play", post(play))
+        .route("/api/ping", post(ping))
         .route("/api/game_state", get(game_state))
+        .route("/api/board.svg", get(board_svg))
+        .route("/api/valid_actions", get(valid_actions))
+        .route("/api/missing", get(missing_cards))
+        .route("/api/card_counts", get(card_counts))
+        .route("/api/illegal_moves", get(illegal_moves))
+        .route("/api/turn_order", get(turn_order))
+        .route("/api/turn_timer", get(turn_timer))
+        .route("/api/winner", get(winner))
+        .route("/api/watch_any", get(watch_any))
         .route("/api/my_hand", get(hand_of_player))
+        .route("/api/my_move_count", get(move_count))
+        .route("/api/my_turn", get(my_turn))
+        .route("/api/hint", get(hint))
+        .route("/api/my_deal", get(dealt_cards))
+        .route("/api/deal_sequence", get(deal_sequence))
+        .route("/api/placement", get(placement))
         .route("/api/last_move", get(last_move))
-        .fallback_service(serve_dir)
-        .with_state(state)
+        .route("/api/seed", get(seed))
+        .route("/api/public_key", get(public_key))
+        .route("/api/events", get(events))
+        .route("/api/export", get(export))
+        .route("/api/whoami", get(whoami))
+        .route("/api/me", get(me))
+        .route("/api/stats", get(player_stats));
+    #[cfg(feature = "debug")]
+    let router = router.route("/api/debug/room", get(debug_room));
+    let router = router.route("/api/*path", any(unknown_endpoint));
+    let router = match frontend_path {
+        Some(path) => router.fallback_service(ServeDir::new(path)),
+        None => router,
+    };
+    router.with_state(state)
+}
+
+/// Create a router exposing the server's metrics in the Prometheus text
+/// format at `path`, for example `"/metrics"`. The endpoint is not
+/// authenticated.
+///
+/// This installs the global metrics recorder, so it must be called at most
+/// once, and before [`badam_sat_router`] for no metrics to be lost. Merge the
+/// returned router with the game router to serve both from the same address.
+pub fn metrics_router(path: &str) -> Result<Router, BuildError> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full("game_duration_seconds".into()),
+            &[60.0, 300.0, 600.0, 1200.0, 1800.0, 3600.0],
+        )?
+        .install_recorder()?;
+    Ok(Router::new().route(path, get(move || async move { handle.render() })))
+}
+
+/// Create a layer that lets browser clients on other origins call the API,
+/// for example when the frontend is hosted separately from the server.
+///
+/// `allow_origin` is a comma-separated list of origins, such as
+/// `"https://example.com,http://localhost:8000"`, or `"*"` for any origin.
+/// An empty list allows no other origin, leaving only same-origin requests
+/// working. Preflight `OPTIONS` requests are answered by the layer. Add it
+/// to the game router with [`Router::layer`], typically from an
+/// `--allow-origin` command line flag.
+pub fn cors_layer(allow_origin: &str) -> Result<CorsLayer, InvalidHeaderValue> {
+    let origins = if allow_origin.trim() == "*" {
+        AllowOrigin::from(Any)
+    } else {
+        let origins = allow_origin
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .map(HeaderValue::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+    Ok(CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods([Method::GET, Method::POST, Method::PATCH])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            HeaderName::from_static("idempotency-key"),
+        ]))
+}
+
+/// Limit how often each client IP address may create and join rooms, in
+/// requests per minute, typically from `--create-rate` and `--join-rate`
+/// command line flags. Requests over the limit are answered with
+/// `429 Too Many Requests`.
+///
+/// `POST /api/create_room` counts against `create_per_minute`, while
+/// `POST /api/join` and `POST /api/quickplay` count against
+/// `join_per_minute`. Bursts of up to a minute's worth of requests are
+/// allowed. Routes are matched by the end of their path, so the router may
+/// be nested under a prefix such as `/badam_sat` before or after limiting it.
+///
+/// The client address is taken from the connection, so the router must be
+/// served with `into_make_service_with_connect_info::<SocketAddr>()`.
+/// Requests without connection information cannot be limited, which is
+/// logged as a warning the first time it happens.
+pub fn rate_limit(router: Router, create_per_minute: u32, join_per_minute: u32) -> Router {
+    let create = RateLimiter::new(create_per_minute);
+    let join = RateLimiter::new(join_per_minute);
+    let warned = Arc::new(AtomicBool::new(false));
+    router.layer(middleware::from_fn(
+        move |connect_info: Option<ConnectInfo<SocketAddr>>, request: Request<_>, next: Next<_>| {
+            let path = request.uri().path();
+            let limiter = if path.ends_with("/api/create_room") {
+                Some(create.clone())
+            } else if path.ends_with("/api/join") || path.ends_with("/api/quickplay") {
+                Some(join.clone())
+            } else {
+                None
+            };
+            let warned = warned.clone();
+            async move {
+                match (limiter, connect_info) {
+                    (Some(limiter), Some(ConnectInfo(addr))) => {
+                        if !limiter.try_acquire(addr.ip()) {
+                            return Error::TooManyRequests.into_response();
+                        }
+                    }
+                    (Some(_), None) => {
+                        if !warned.swap(true, Ordering::Relaxed) {
+                            log::warn!(
+                                "no client address to rate limit by, serve the router with \
+                                 into_make_service_with_connect_info::<SocketAddr>()"
+                            );
+                        }
+                    }
+                    (None, _) => (),
+                }
+                next.run(request).await
+            }
+        },
+    ))
+}
+
+/// Resolve a comma-separated list of addresses, such as
+/// `"0.0.0.0:8080,[::]:8080"` or `"localhost:8080"`, into socket addresses.
+///
+/// Each resolved address can be bound with a clone of the same
+/// [`badam_sat_router`], so that all of them share the same rooms. Addresses
+/// that fail to resolve are logged and skipped; an error is returned only if
+/// none of them resolve.
+pub fn resolve_addresses(addresses: &str) -> io::Result<Vec<SocketAddr>> {
+    let mut resolved = Vec::new();
+    for address in addresses.split(',').map(str::trim) {
+        match address.to_socket_addrs() {
+            Ok(socket_addrs) => resolved.extend(socket_addrs),
+            Err(err) => log::warn!("could not resolve address {address:?}: {err}"),
+        }
+    }
+    if resolved.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("none of the addresses in {addresses:?} could be resolved"),
+        ));
+    }
+    Ok(resolved)
+}
+
+/// Wait for a shutdown signal; SIGINT, or SIGTERM on Unix platforms.
+///
+/// Meant to be passed to `axum::Server::with_graceful_shutdown` so that the
+/// server stops accepting new connections and lets in-flight requests finish.
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install SIGINT handler");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => log::info!("received SIGINT, shutting down"),
+        _ = terminate => log::info!("received SIGTERM, shutting down"),
+    }
+    log::info!("waiting for in-flight requests to finish");
 }
 
 type RouterServerMessageSender = Arc<mpsc::Sender<RouterServerMessage>>;
 
 enum RouterServerMessage {
     CreateRoom {
-        players: usize,
-        decks: usize,
-        responder: oneshot::Sender<Result<Uuid, Error>>,
+        config: RoomConfig,
+        password: Option<String>,
+        responder: oneshot::Sender<Result<(Uuid, Option<String>), Error>>,
     },
     JoinRoom {
         room: Uuid,
+        player_key: Option<Uuid>,
+        password: Option<String>,
         secret_key: AsymmetricSecretKey<V4>,
-        responder: oneshot::Sender<Result<String, Error>>,
+        responder: oneshot::Sender<Result<(String, usize), Error>>,
+    },
+    CreateAndJoin {
+        config: RoomConfig,
+        password: Option<String>,
+        player_key: Option<Uuid>,
+        secret_key: AsymmetricSecretKey<V4>,
+        responder: oneshot::Sender<Result<Seat, Error>>,
+    },
+    Quickplay {
+        config: RoomConfig,
+        player_key: Option<Uuid>,
+        secret_key: AsymmetricSecretKey<V4>,
+        responder: oneshot::Sender<Result<Seat, Error>>,
+    },
+    Stats {
+        player_key: Uuid,
+        responder: oneshot::Sender<PlayerStats>,
     },
     Play {
         action: Action,
         player: usize,
         room: Uuid,
+        idempotency_key: Option<Uuid>,
+        responder: oneshot::Sender<Result<PlayOutcome, Error>>,
+    },
+    SetReady {
+        player: usize,
+        room: Uuid,
+        ready: bool,
+        responder: oneshot::Sender<Result<(), Error>>,
+    },
+    Start {
+        player: usize,
+        room: Uuid,
+        responder: oneshot::Sender<Result<(), Error>>,
+    },
+    Confirm {
+        player: usize,
+        room: Uuid,
+        responder: oneshot::Sender<Result<Confirmation, Error>>,
+    },
+    Kick {
+        player: usize,
+        room: Uuid,
+        kicked: usize,
+        discard_cards: bool,
+        confirmation: Uuid,
+        responder: oneshot::Sender<Result<(), Error>>,
+    },
+    Reconfigure {
+        player: usize,
+        room: Uuid,
+        players: Option<usize>,
+        decks: Option<usize>,
+        teams: Option<Vec<usize>>,
+        confirmation: Uuid,
         responder: oneshot::Sender<Result<(), Error>>,
     },
     GetHand {
@@ -86,61 +396,361 @@ enum RouterServerMessage {
         room: Uuid,
         responder: oneshot::Sender<Result<Vec<Card>, Error>>,
     },
+    MoveCount {
+        player: usize,
+        room: Uuid,
+        responder: oneshot::Sender<Result<usize, Error>>,
+    },
+    MyTurn {
+        player: usize,
+        room: Uuid,
+        responder: oneshot::Sender<Result<MyTurn, Error>>,
+    },
+    Hint {
+        player: usize,
+        room: Uuid,
+        responder: oneshot::Sender<Result<Option<Card>, Error>>,
+    },
+    DealtCards {
+        player: usize,
+        room: Uuid,
+        responder: oneshot::Sender<Result<Vec<Card>, Error>>,
+    },
+    DealSequence {
+        room: Uuid,
+        responder: oneshot::Sender<Result<Vec<usize>, Error>>,
+    },
+    Placement {
+        player: usize,
+        room: Uuid,
+        card: Card,
+        responder: oneshot::Sender<Result<Option<usize>, Error>>,
+    },
     LastMove {
         room: Uuid,
         responder: oneshot::Sender<Result<Action, Error>>,
     },
+    Ping {
+        room: Uuid,
+        responder: oneshot::Sender<Result<(), Error>>,
+    },
+    RoomExists {
+        room: Uuid,
+        responder: oneshot::Sender<bool>,
+    },
+    ValidActions {
+        room: Uuid,
+        responder: oneshot::Sender<Result<ValidActions, Error>>,
+    },
+    MissingCards {
+        room: Uuid,
+        responder: oneshot::Sender<Result<HashMap<Suit, Vec<Card>>, Error>>,
+    },
+    CardCounts {
+        room: Uuid,
+        responder: oneshot::Sender<Result<Vec<usize>, Error>>,
+    },
+    IllegalMoves {
+        room: Uuid,
+        responder: oneshot::Sender<Result<Vec<usize>, Error>>,
+    },
+    TurnOrder {
+        room: Uuid,
+        responder: oneshot::Sender<Result<TurnOrder, Error>>,
+    },
+    TurnTimer {
+        room: Uuid,
+        responder: oneshot::Sender<Result<Option<TurnTimer>, Error>>,
+    },
+    Winner {
+        room: Uuid,
+        responder: oneshot::Sender<Result<Winner, Error>>,
+    },
+    PickActiveRoom(oneshot::Sender<Result<Uuid, Error>>),
     GameState {
         room: Uuid,
         responder: oneshot::Sender<Result<GameState, Error>>,
     },
+    #[cfg(feature = "debug")]
+    Debug {
+        room: Uuid,
+        responder: oneshot::Sender<Result<String, Error>>,
+    },
+    Seed {
+        room: Uuid,
+        responder: oneshot::Sender<Result<[u8; 32], Error>>,
+    },
+    Export {
+        room: Uuid,
+        responder: oneshot::Sender<Result<GameExport, Error>>,
+    },
+    Events {
+        room: Uuid,
+        since: usize,
+        catch_up: bool,
+        responder: oneshot::Sender<Result<(Vec<RoomEvent>, usize), Error>>,
+    },
 }
 
 async fn create_room(
     State(state): State<ServerState>,
     Json(room_request): Json<NewRoomRequest>,
-) -> Result<Json<RoomPayload>, Error> {
+) -> Result<Json<NewRoomResponse>, Error> {
     log::info!("received create room request");
+    if room_request.join {
+        // joining in the same message keeps anyone else from taking the seat
+        let (responder, receiver) = oneshot::channel();
+        state
+            .sender
+            .send(RouterServerMessage::CreateAndJoin {
+                config: room_request.config,
+                password: room_request.password,
+                player_key: room_request.player_key,
+                secret_key: state.key_pair.secret.clone(),
+                responder,
+            })
+            .await?;
+        return Ok(Json(seat_response(receiver.await??)));
+    }
     let (responder, receiver) = oneshot::channel();
     state
         .sender
         .send(RouterServerMessage::CreateRoom {
-            players: room_request.players,
-            decks: room_request.decks,
+            config: room_request.config,
+            password: room_request.password,
             responder,
         })
         .await?;
-    receiver.await?.map(|room_id| Json(RoomPayload { room_id }))
+    let (room_id, seed_commitment) = receiver.await??;
+    Ok(Json(NewRoomResponse {
+        room_id,
+        seed_commitment,
+        join: None,
+    }))
+}
+
+/// Describe the `seat` a player was given in a new or open room.
+fn seat_response(seat: Seat) -> NewRoomResponse {
+    NewRoomResponse {
+        room_id: seat.room_id,
+        seed_commitment: seat.seed_commitment,
+        join: Some(JoinSuccess {
+            token_type: Some("Bearer".into()),
+            token: Some(seat.token),
+            player_id: Some(seat.player_id),
+            role: Role::Player,
+        }),
+    }
 }
 
 async fn join(
     State(state): State<ServerState>,
-    Json(payload): Json<RoomPayload>,
+    Json(payload): Json<JoinPayload>,
 ) -> Result<Json<JoinSuccess>, Error> {
     log::info!("received join request");
+    match join_room(
+        &state,
+        payload.room_id,
+        payload.player_key,
+        payload.password,
+    )
+    .await
+    {
+        Err(Error::RoomFull) if payload.spectate_if_full => Ok(Json(JoinSuccess {
+            token_type: None,
+            token: None,
+            player_id: None,
+            role: Role::Spectator,
+        })),
+        result => result.map(Json),
+    }
+}
+
+/// Join the room `room_id` as a new player, identified across rooms by the
+/// `player_key` if one is given, with the room's `password` if it is private.
+async fn join_room(
+    state: &ServerState,
+    room_id: Uuid,
+    player_key: Option<Uuid>,
+    password: Option<String>,
+) -> Result<JoinSuccess, Error> {
     let (responder, receiver) = oneshot::channel();
     state
         .sender
         .send(RouterServerMessage::JoinRoom {
-            room: payload.room_id,
-            secret_key: state.key_pair.secret,
+            room: room_id,
+            player_key,
+            password,
+            secret_key: state.key_pair.secret.clone(),
             responder,
         })
         .await?;
-    receiver.await?.map(|token| {
-        Json(JoinSuccess {
-            token_type: "Bearer".into(),
-            token,
-        })
+    receiver.await?.map(|(token, player_id)| JoinSuccess {
+        token_type: Some("Bearer".into()),
+        token: Some(token),
+        player_id: Some(player_id),
+        role: Role::Player,
     })
 }
 
-async fn play(
+/// Join a room with the requested settings that has a free seat, creating one
+/// if needed, for playing without sharing room Ids.
+async fn quickplay(
+    State(state): State<ServerState>,
+    Json(payload): Json<QuickplayPayload>,
+) -> Result<Json<NewRoomResponse>, Error> {
+    log::info!("received quickplay request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Quickplay {
+            config: payload.config,
+            player_key: payload.player_key,
+            secret_key: state.key_pair.secret.clone(),
+            responder,
+        })
+        .await?;
+    Ok(Json(seat_response(receiver.await??)))
+}
+
+async fn player_stats(
+    State(state): State<ServerState>,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<PlayerStats>, Error> {
+    log::info!("received stats request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Stats {
+            player_key: query.player_key,
+            responder,
+        })
+        .await?;
+    Ok(Json(receiver.await?))
+}
+
+async fn ready(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+    Json(payload): Json<ReadyPayload>,
+) -> Result<StatusCode, Error> {
+    log::info!("received ready request from player {}", player.player_id);
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::SetReady {
+            player: player.player_id,
+            room: player.room_id,
+            ready: payload.ready,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(|_| StatusCode::OK)
+}
+
+async fn start(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+) -> Result<StatusCode, Error> {
+    log::info!("received start request from player {}", player.player_id);
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Start {
+            player: player.player_id,
+            room: player.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(|_| StatusCode::OK)
+}
+
+/// Get a confirmation for the host's next kick or change of settings. It
+/// expires after a few seconds and can be used only once.
+async fn host_confirmation(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+) -> Result<Json<Confirmation>, Error> {
+    log::info!(
+        "received confirmation request from player {}",
+        player.player_id
+    );
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Confirm {
+            player: player.player_id,
+            room: player.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+async fn kick(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+    Json(payload): Json<KickPayload>,
+) -> Result<StatusCode, Error> {
+    log::info!("received kick request from player {}", player.player_id);
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Kick {
+            player: player.player_id,
+            room: player.room_id,
+            kicked: payload.player,
+            discard_cards: payload.discard_cards,
+            confirmation: payload.confirmation,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(|_| StatusCode::OK)
+}
+
+async fn reconfigure(
     player: AuthenticatedPlayer,
     State(state): State<ServerState>,
-    Json(action): Json<Action>,
+    Json(payload): Json<ReconfigurePayload>,
 ) -> Result<StatusCode, Error> {
+    log::info!(
+        "received reconfigure request from player {}",
+        player.player_id
+    );
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Reconfigure {
+            player: player.player_id,
+            room: player.room_id,
+            players: payload.players,
+            decks: payload.decks,
+            teams: payload.teams,
+            confirmation: payload.confirmation,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(|_| StatusCode::OK)
+}
+
+async fn play(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+    headers: HeaderMap,
+    ActionPayload(action): ActionPayload,
+) -> Result<Json<PlayOutcome>, Error> {
     log::info!("received play request from player {}", player.player_id);
+    // retried requests with the same key get the original result instead of
+    // being played again
+    let idempotency_key = headers
+        .get("Idempotency-Key")
+        .map(|key| {
+            key.to_str()
+                .ok()
+                .and_then(|key| Uuid::try_parse(key).ok())
+                .ok_or(Error::InvalidIdempotencyKey)
+        })
+        .transpose()?;
     let (responder, receiver) = oneshot::channel();
     state
         .sender
@@ -148,16 +758,19 @@ async fn play(
             action,
             player: player.player_id,
             room: player.room_id,
+            idempotency_key,
             responder,
         })
         .await?;
-    receiver.await?.map(|_| StatusCode::OK)
+    receiver.await?.map(Json)
 }
 
+/// Get a snapshot of the room's game. This always answers straight away with
+/// the current state, so clients poll it to follow the game.
 async fn game_state(
     State(state): State<ServerState>,
-    Query(payload): Query<RoomPayload>,
-) -> Result<Json<GameState>, Error> {
+    Query(payload): Query<GameStateQuery>,
+) -> Result<Response, Error> {
     log::info!("received game_state request");
     let (responder, receiver) = oneshot::channel();
     state
@@ -167,35 +780,84 @@ async fn game_state(
             responder,
         })
         .await?;
-    receiver.await?.map(Json)
+    let labels = payload.locale.map(Locale::labels);
+    receiver.await?.map(|game_state| match payload.format {
+        StateFormat::Full => Json(Localized {
+            state: game_state,
+            labels,
+        })
+        .into_response(),
+        StateFormat::Compact => Json(Localized {
+            state: CompactGameState::from(game_state),
+            labels,
+        })
+        .into_response(),
+    })
 }
 
-async fn hand_of_player(
+/// Get a picture of the room's playing area, for embedding in chats and
+/// dashboards.
+async fn board_svg(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Response, Error> {
+    log::info!("received board svg request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::GameState {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    let game_state = receiver.await??;
+    let svg = render::board_svg(&game_state.playing_area);
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response())
+}
+
+/// Keep the player's room open while they take a long turn. Any other request
+/// to the room also counts as activity.
+async fn ping(
     player: AuthenticatedPlayer,
     State(state): State<ServerState>,
-) -> Result<Json<Vec<Card>>, Error> {
-    log::info!("received hand request from player {}", player.player_id);
+) -> Result<StatusCode, Error> {
+    log::debug!("received ping from player {}", player.player_id);
     let (responder, receiver) = oneshot::channel();
     state
         .sender
-        .send(RouterServerMessage::GetHand {
-            player: player.player_id,
+        .send(RouterServerMessage::Ping {
             room: player.room_id,
             responder,
         })
         .await?;
+    receiver.await?.map(|_| StatusCode::OK)
+}
+
+async fn valid_actions(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<ValidActions>, Error> {
+    log::info!("received valid actions request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::ValidActions {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
     receiver.await?.map(Json)
 }
 
-async fn last_move(
+async fn missing_cards(
     State(state): State<ServerState>,
     Query(payload): Query<RoomPayload>,
-) -> Result<Json<Action>, Error> {
-    log::info!("received last move request");
+) -> Result<Json<HashMap<Suit, Vec<Card>>>, Error> {
+    log::info!("received missing cards request");
     let (responder, receiver) = oneshot::channel();
     state
         .sender
-        .send(RouterServerMessage::LastMove {
+        .send(RouterServerMessage::MissingCards {
             room: payload.room_id,
             responder,
         })
@@ -203,21 +865,487 @@ async fn last_move(
     receiver.await?.map(Json)
 }
 
-#[derive(Debug, Serialize)]
-struct JoinSuccess {
-    token_type: String,
-    token: String,
+async fn card_counts(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<Vec<usize>>, Error> {
+    log::info!("received card counts request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::CardCounts {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-struct RoomPayload {
-    room_id: Uuid,
+/// Get the number of illegal moves each player attempted.
+async fn illegal_moves(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<Vec<usize>>, Error> {
+    log::info!("received illegal moves request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::IllegalMoves {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
 }
 
-#[derive(Debug, Deserialize)]
+async fn turn_order(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<TurnOrder>, Error> {
+    log::info!("received turn order request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::TurnOrder {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+/// Get the player whose turn it is and the seconds left on their turn, or
+/// `null` when there is no timed turn going on.
+async fn turn_timer(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<Option<TurnTimer>>, Error> {
+    log::info!("received turn timer request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::TurnTimer {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+/// Get the winner of the room's game. Like every other endpoint this answers
+/// straight away, with a `null` winner while the game is still going on.
+async fn winner(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<Winner>, Error> {
+    log::info!("received winner request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Winner {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+/// Get a room to spectate, the one with the most recent activity among the
+/// rooms whose game is in progress.
+async fn watch_any(State(state): State<ServerState>) -> Result<Json<RoomPayload>, Error> {
+    log::info!("received watch any request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::PickActiveRoom(responder))
+        .await?;
+    let room_id = receiver.await??;
+    Ok(Json(RoomPayload { room_id }))
+}
+
+async fn hand_of_player(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<Card>>, Error> {
+    log::info!("received hand request from player {}", player.player_id);
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::GetHand {
+            player: player.player_id,
+            room: player.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+/// Get the player's hand together with the cards they can play this turn, in
+/// a single request.
+async fn my_turn(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+) -> Result<Json<MyTurn>, Error> {
+    log::info!("received my turn request from player {}", player.player_id);
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::MyTurn {
+            player: player.player_id,
+            room: player.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+/// Suggest a card for the player to play this turn. This is a simple hint
+/// for beginners, not the best move. Answers with no content when the player
+/// can only pass.
+async fn hint(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+) -> Result<Response, Error> {
+    log::info!("received hint request from player {}", player.player_id);
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Hint {
+            player: player.player_id,
+            room: player.room_id,
+            responder,
+        })
+        .await?;
+    Ok(match receiver.await?? {
+        Some(card) => Json(card).into_response(),
+        None => StatusCode::NO_CONTENT.into_response(),
+    })
+}
+
+/// Get the cards dealt to the player, in the order they were dealt.
+async fn dealt_cards(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<Card>>, Error> {
+    log::info!("received deal request from player {}", player.player_id);
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::DealtCards {
+            player: player.player_id,
+            room: player.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+/// Get the seat each card was dealt to, in the order of dealing.
+async fn deal_sequence(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<Vec<usize>>, Error> {
+    log::info!("received deal sequence request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::DealSequence {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+async fn move_count(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+) -> Result<Json<usize>, Error> {
+    log::info!(
+        "received move count request from player {}",
+        player.player_id
+    );
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::MoveCount {
+            player: player.player_id,
+            room: player.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+async fn placement(
+    player: AuthenticatedPlayer,
+    State(state): State<ServerState>,
+    Query(query): Query<PlacementQuery>,
+) -> Result<Json<PlacementPayload>, Error> {
+    log::info!(
+        "received placement request from player {}",
+        player.player_id
+    );
+    let card = serde_json::from_str(&query.card).map_err(|_| Error::InvalidCard)?;
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Placement {
+            player: player.player_id,
+            room: player.room_id,
+            card,
+            responder,
+        })
+        .await?;
+    receiver
+        .await?
+        .map(|stack| Json(PlacementPayload { stack }))
+}
+
+async fn last_move(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<Action>, Error> {
+    log::info!("received last move request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::LastMove {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+#[cfg(feature = "debug")]
+async fn debug_room(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<String, Error> {
+    log::warn!("received debug request for room {}", payload.room_id);
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Debug {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?
+}
+
+/// Get the public key the player tokens are signed with, in the PASERK
+/// `k4.public` format, so that other services can verify tokens offline.
+async fn public_key(State(state): State<ServerState>) -> Json<PublicKeyPayload> {
+    log::info!("received public key request");
+    let mut paserk = String::new();
+    FormatAsPaserk::fmt(&state.key_pair.public, &mut paserk)
+        .expect("writing to a string cannot fail");
+    Json(PublicKeyPayload { paserk })
+}
+
+async fn seed(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<SeedPayload>, Error> {
+    log::info!("received seed request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Seed {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(|seed| {
+        Json(SeedPayload {
+            seed: to_hex(&seed),
+        })
+    })
+}
+
+async fn export(
+    State(state): State<ServerState>,
+    Query(payload): Query<RoomPayload>,
+) -> Result<Json<GameExport>, Error> {
+    log::info!("received export request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Export {
+            room: payload.room_id,
+            responder,
+        })
+        .await?;
+    receiver.await?.map(Json)
+}
+
+async fn events(
+    State(state): State<ServerState>,
+    Query(query): Query<EventsQuery>,
+) -> Result<Json<EventsPayload>, Error> {
+    log::info!("received events request");
+    let (responder, receiver) = oneshot::channel();
+    state
+        .sender
+        .send(RouterServerMessage::Events {
+            room: query.room_id,
+            since: query.since,
+            catch_up: query.catch_up,
+            responder,
+        })
+        .await?;
+    receiver
+        .await?
+        .map(|(events, next)| Json(EventsPayload { events, next }))
+}
+
+async fn whoami(State(state): State<ServerState>) -> Json<WhoAmI> {
+    Json(WhoAmI {
+        instance_id: state.instance_id,
+    })
+}
+
+async fn me(player: AuthenticatedPlayer) -> Json<Me> {
+    Json(Me {
+        player_id: player.player_id,
+        room_id: player.room_id,
+    })
+}
+
+async fn unknown_endpoint() -> Error {
+    Error::UnknownEndpoint
+}
+
+#[derive(Debug, Deserialize)]
+struct PlacementQuery {
+    /// JSON encoded card, the same as in the player's hand.
+    card: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PlacementPayload {
+    /// Index of the stack in the playing area that would accept the card.
+    stack: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KickPayload {
+    player: usize,
+    /// Leave the kicked player out of the finishing order.
+    #[serde(default)]
+    discard_cards: bool,
+    /// Nonce from `GET /api/host/confirm`.
+    confirmation: Uuid,
+}
+
+/// Room settings to change, leaving out the ones that stay the same.
+#[derive(Debug, Deserialize)]
+struct ReconfigurePayload {
+    players: Option<usize>,
+    decks: Option<usize>,
+    /// New team of each seat, needed when a room with teams changes its
+    /// number of players.
+    teams: Option<Vec<usize>>,
+    /// Nonce from `GET /api/host/confirm`.
+    confirmation: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct GameStateQuery {
+    room_id: Uuid,
+    #[serde(default)]
+    format: StateFormat,
+    /// Include card labels in this language in the response.
+    locale: Option<Locale>,
+}
+
+/// A response with optional localized card labels alongside.
+#[derive(Debug, Serialize)]
+struct Localized<T> {
+    #[serde(flatten)]
+    state: T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<Labels>,
+}
+
+/// Representation of the game state to respond with.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum StateFormat {
+    #[default]
+    Full,
+    Compact,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReadyPayload {
+    ready: bool,
+}
+
+#[derive(Debug, Deserialize)]
 struct NewRoomRequest {
-    players: usize,
-    decks: usize,
+    #[serde(flatten)]
+    config: RoomConfig,
+    /// Whether to seat the creator as the room's first player right away,
+    /// so that nobody else can take the first seat.
+    #[serde(default)]
+    join: bool,
+    /// Identity of the creator across rooms, if they join right away.
+    #[serde(default)]
+    player_key: Option<Uuid>,
+    /// Make the room private, only letting in players who give the same
+    /// password when joining.
+    #[serde(default)]
+    password: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuickplayPayload {
+    #[serde(flatten)]
+    config: RoomConfig,
+    /// Identity of the player across rooms.
+    #[serde(default)]
+    player_key: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatsQuery {
+    player_key: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    room_id: Uuid,
+    /// Index of the first event to return.
+    #[serde(default)]
+    since: usize,
+    /// Start with the moves played so far, for clients that missed them.
+    #[serde(default)]
+    catch_up: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct EventsPayload {
+    events: Vec<RoomEvent>,
+    /// Index to request the following events from.
+    next: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct WhoAmI {
+    instance_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+struct SeedPayload {
+    seed: String,
+}
+
+#[derive(Debug, Serialize)]
+struct PublicKeyPayload {
+    paserk: String,
 }
 
 /// Represents a player that has been verified based on their PASETO token.
@@ -244,17 +1372,23 @@ impl ServerState {
             None,
         )
         .map_err(|_| Error::InvalidToken)?;
+        let instance_id = trusted_token
+            .payload_claims()
+            .unwrap()
+            .get_claim("instance_id")
+            .and_then(|id| serde_json::from_value::<Uuid>(id.clone()).ok())
+            .ok_or(Error::InvalidToken)?;
+        if instance_id != self.instance_id {
+            return Err(Error::WrongInstance);
+        }
         let player = AuthenticatedPlayer {
             token: token.to_owned(),
             player_id: trusted_token
                 .payload_claims()
                 .unwrap()
                 .get_claim("sub")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .parse()
-                .unwrap(),
+                .and_then(|sub| sub.as_str()?.parse().ok())
+                .ok_or(Error::InvalidToken)?,
             room_id: serde_json::from_value::<Uuid>(
                 trusted_token
                     .payload_claims()
@@ -269,6 +1403,27 @@ impl ServerState {
     }
 }
 
+/// An [`Action`] in the request body, rejected with [`Error::InvalidAction`]
+/// instead of axum's plain text rejection when it cannot be parsed.
+struct ActionPayload(Action);
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for ActionPayload
+where
+    Json<Action>: FromRequest<S, B>,
+    S: Send + Sync,
+    B: Send + 'static,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(action) = Json::<Action>::from_request(req, state)
+            .await
+            .map_err(|_| Error::InvalidAction)?;
+        Ok(ActionPayload(action))
+    }
+}
+
 #[async_trait]
 impl FromRequestParts<ServerState> for AuthenticatedPlayer {
     type Rejection = Error;
@@ -281,6 +1436,198 @@ impl FromRequestParts<ServerState> for AuthenticatedPlayer {
             .extract::<TypedHeader<Authorization<Bearer>>>()
             .await
             .map_err(|_| Error::InvalidToken)?;
-        state.verify(token.token())
+        let player = state.verify(token.token())?;
+        // tokens of rooms that are gone are no longer valid
+        let (responder, receiver) = oneshot::channel();
+        state
+            .sender
+            .send(RouterServerMessage::RoomExists {
+                room: player.room_id,
+                responder,
+            })
+            .await?;
+        if receiver.await? {
+            Ok(player)
+        } else {
+            Err(Error::InvalidToken)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::SocketAddr, sync::Arc};
+
+    use axum::{
+        body::Body,
+        extract::{connect_info::MockConnectInfo, FromRequest, FromRequestParts},
+        http::{
+            header::{
+                ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_REQUEST_METHOD, AUTHORIZATION,
+                CONTENT_TYPE, ORIGIN,
+            },
+            Request, StatusCode,
+        },
+        response::IntoResponse,
+        routing::{get, post},
+        Router,
+    };
+    use pasetors::{
+        claims::Claims,
+        keys::{AsymmetricKeyPair, Generate},
+        version4::V4,
+    };
+    use tokio::sync::mpsc;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use super::{
+        cors_layer, rate_limit, ActionPayload, AuthenticatedPlayer, RouterServerMessage,
+        ServerState,
+    };
+    use crate::errors::Error;
+
+    #[tokio::test]
+    async fn test_garbage_action_is_rejected() {
+        let request = Request::builder()
+            .method("POST")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(r#"{"Play": 7"#))
+            .unwrap();
+        let rejection = ActionPayload::from_request(request, &())
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(rejection, Error::InvalidAction));
+        assert_eq!(rejection.into_response().status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_token_of_removed_room_is_rejected() {
+        let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+        let instance_id = Uuid::new_v4();
+        let mut claims = Claims::new().unwrap();
+        claims.subject("0").unwrap();
+        claims
+            .add_additional("room_id", serde_json::to_value(Uuid::new_v4()).unwrap())
+            .unwrap();
+        claims
+            .add_additional("instance_id", serde_json::to_value(instance_id).unwrap())
+            .unwrap();
+        let token = pasetors::public::sign(&key_pair.secret, &claims, None, None).unwrap();
+
+        // a server whose rooms have all been removed
+        let (sender, mut receiver) = mpsc::channel(1);
+        tokio::spawn(async move {
+            while let Some(msg) = receiver.recv().await {
+                if let RouterServerMessage::RoomExists { responder, .. } = msg {
+                    responder.send(false).unwrap();
+                }
+            }
+        });
+        let state = ServerState {
+            sender: Arc::new(sender),
+            key_pair,
+            instance_id,
+        };
+        assert!(state.verify(&token).is_ok());
+
+        let (mut parts, _) = Request::builder()
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .body(())
+            .unwrap()
+            .into_parts();
+        assert!(matches!(
+            AuthenticatedPlayer::from_request_parts(&mut parts, &state).await,
+            Err(Error::InvalidToken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        let router = Router::new()
+            .route("/api/ping", get(|| async {}))
+            .layer(cors_layer("https://example.com, http://localhost:8000").unwrap());
+        let preflight = |origin: &'static str| {
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/api/ping")
+                .header(ORIGIN, origin)
+                .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(preflight("https://example.com"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://example.com"
+        );
+
+        let response = router.oneshot(preflight("https://evil.com")).await.unwrap();
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_per_address() {
+        let routes = Router::new()
+            .route("/api/create_room", post(|| async {}))
+            .route("/api/join", post(|| async {}))
+            .route("/api/ping", post(|| async {}));
+        let router = rate_limit(routes.clone(), 1, 2);
+        let request = |uri: &'static str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let from = |addr: [u8; 4]| {
+            router
+                .clone()
+                .layer(MockConnectInfo(SocketAddr::from((addr, 4000))))
+        };
+
+        let client = from([10, 0, 0, 1]);
+        let send = |uri| client.clone().oneshot(request(uri));
+        assert_eq!(
+            send("/api/create_room").await.unwrap().status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            send("/api/create_room").await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(send("/api/join").await.unwrap().status(), StatusCode::OK);
+        assert_eq!(send("/api/join").await.unwrap().status(), StatusCode::OK);
+        assert_eq!(
+            send("/api/join").await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        assert_eq!(send("/api/ping").await.unwrap().status(), StatusCode::OK);
+
+        // the routes are still limited when nested under a prefix
+        let nested = rate_limit(Router::new().nest("/badam_sat", routes), 1, 2)
+            .layer(MockConnectInfo(SocketAddr::from(([10, 0, 0, 3], 4000))));
+        let send = |uri| nested.clone().oneshot(request(uri));
+        assert_eq!(
+            send("/badam_sat/api/create_room").await.unwrap().status(),
+            StatusCode::OK
+        );
+        assert_eq!(
+            send("/badam_sat/api/create_room").await.unwrap().status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+
+        let response = from([10, 0, 0, 2])
+            .oneshot(request("/api/create_room"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
     }
 }
@@ -0,0 +1,109 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How often the buckets that have refilled completely are forgotten.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Token bucket per client IP address, refilled at `per_minute` tokens a
+/// minute and holding at most `per_minute` tokens.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    per_minute: u32,
+    buckets: Arc<Mutex<Buckets>>,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    by_ip: HashMap<IpAddr, Bucket>,
+    pruned: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32) -> Self {
+        RateLimiter {
+            per_minute,
+            buckets: Arc::new(Mutex::new(Buckets {
+                by_ip: HashMap::new(),
+                pruned: Instant::now(),
+            })),
+        }
+    }
+
+    /// Take a token from the bucket of `ip`, returning whether one was left.
+    pub fn try_acquire(&self, ip: IpAddr) -> bool {
+        self.try_acquire_at(ip, Instant::now())
+    }
+
+    fn try_acquire_at(&self, ip: IpAddr, now: Instant) -> bool {
+        let capacity = f64::from(self.per_minute);
+        let mut buckets = self.buckets.lock().expect("rate limiter lock poisoned");
+        if now.saturating_duration_since(buckets.pruned) >= PRUNE_INTERVAL {
+            buckets
+                .by_ip
+                .retain(|_, bucket| self.refilled(bucket, now) < capacity);
+            buckets.pruned = now;
+        }
+        let bucket = buckets.by_ip.entry(ip).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        let capacity = f64::from(self.per_minute);
+        (bucket.tokens + elapsed * capacity / 60.0).min(capacity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills() {
+        let limiter = RateLimiter::new(2);
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        let other = IpAddr::from([127, 0, 0, 2]);
+        let start = Instant::now();
+        assert!(limiter.try_acquire_at(ip, start));
+        assert!(limiter.try_acquire_at(ip, start));
+        assert!(!limiter.try_acquire_at(ip, start));
+        assert!(limiter.try_acquire_at(other, start));
+        assert!(!limiter.try_acquire_at(ip, start + Duration::from_secs(29)));
+        assert!(limiter.try_acquire_at(ip, start + Duration::from_secs(31)));
+        assert!(!limiter.try_acquire_at(ip, start + Duration::from_secs(31)));
+    }
+
+    #[test]
+    fn test_refilled_buckets_are_pruned() {
+        let limiter = RateLimiter::new(2);
+        let start = Instant::now();
+        for last in 0..=255 {
+            assert!(limiter.try_acquire_at(IpAddr::from([10, 0, 0, last]), start));
+        }
+        let later = start + PRUNE_INTERVAL;
+        assert!(limiter.try_acquire_at(IpAddr::from([127, 0, 0, 1]), later));
+        assert_eq!(limiter.buckets.lock().unwrap().by_ip.len(), 1);
+    }
+}
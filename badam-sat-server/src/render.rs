@@ -0,0 +1,157 @@
+use std::fmt::Write;
+
+use badam_sat::games::{CardStack, PlayingArea, StackState};
+use card_deck::standard_deck::{Card, Suit};
+
+const CARD_WIDTH: usize = 60;
+const CARD_HEIGHT: usize = 90;
+const GAP: usize = 10;
+const HEADER_HEIGHT: usize = 30;
+
+/// Render the `playing_area` as an SVG image, with a column of stacks for
+/// each suit showing the highest and lowest card played on each stack.
+pub fn board_svg(playing_area: &PlayingArea) -> String {
+    let suits = Suit::all_suits();
+    let rows = suits
+        .iter()
+        .map(|suit| stacks_of(playing_area, suit).count())
+        .max()
+        .unwrap_or(0);
+    let width = suits.len() * (CARD_WIDTH + GAP) + GAP;
+    let height = HEADER_HEIGHT + rows * (CARD_HEIGHT + GAP) + GAP;
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif" text-anchor="middle">"#
+    );
+    write!(
+        svg,
+        r##"<rect width="{width}" height="{height}" fill="#2e7d32"/>"##
+    )
+    .unwrap();
+    for (column, suit) in suits.iter().enumerate() {
+        let x = GAP + column * (CARD_WIDTH + GAP);
+        let center = x + CARD_WIDTH / 2;
+        write!(
+            svg,
+            r#"<text x="{center}" y="{}" font-size="20" fill="{}">{}</text>"#,
+            HEADER_HEIGHT - 8,
+            suit_color(suit),
+            suit_symbol(suit),
+        )
+        .unwrap();
+        for (row, stack) in stacks_of(playing_area, suit).enumerate() {
+            let y = HEADER_HEIGHT + row * (CARD_HEIGHT + GAP);
+            write_stack(&mut svg, stack, x, y);
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+fn stacks_of<'a>(
+    playing_area: &'a PlayingArea,
+    suit: &'a Suit,
+) -> impl Iterator<Item = &'a CardStack> {
+    playing_area
+        .stacks()
+        .iter()
+        .filter(move |stack| stack.suit() == suit)
+}
+
+/// Draw the `stack` as a card with its top left corner at `x`, `y`. Empty
+/// stacks are drawn as a dashed outline.
+fn write_stack(svg: &mut String, stack: &CardStack, x: usize, y: usize) {
+    let seven = || "7".to_owned();
+    let (high, low) = match stack.stack_state() {
+        StackState::Empty => {
+            write!(
+                svg,
+                r#"<rect x="{x}" y="{y}" width="{CARD_WIDTH}" height="{CARD_HEIGHT}" rx="6" fill="none" stroke="white" stroke-dasharray="4"/>"#
+            )
+            .unwrap();
+            return;
+        }
+        StackState::SevenOnly => (seven(), seven()),
+        StackState::LowOnly(low) => (seven(), rank_label(low)),
+        StackState::HighOnly(high) => (rank_label(high), seven()),
+        StackState::LowAndHigh { low, high } => (rank_label(high), rank_label(low)),
+    };
+    let center = x + CARD_WIDTH / 2;
+    let color = suit_color(stack.suit());
+    write!(
+        svg,
+        r#"<rect x="{x}" y="{y}" width="{CARD_WIDTH}" height="{CARD_HEIGHT}" rx="6" fill="white" stroke="black"/>"#
+    )
+    .unwrap();
+    write!(
+        svg,
+        r#"<text x="{center}" y="{}" font-size="18" fill="{color}">{high}</text>"#,
+        y + 26,
+    )
+    .unwrap();
+    write!(
+        svg,
+        r#"<text x="{center}" y="{}" font-size="18" fill="{color}">{low}</text>"#,
+        y + CARD_HEIGHT - 12,
+    )
+    .unwrap();
+}
+
+fn rank_label(card: &Card) -> String {
+    match card.rank().map(|rank| rank.value()) {
+        Some(1) => "A".to_owned(),
+        Some(11) => "J".to_owned(),
+        Some(12) => "Q".to_owned(),
+        Some(13) => "K".to_owned(),
+        Some(value) => value.to_string(),
+        None => "?".to_owned(),
+    }
+}
+
+fn suit_symbol(suit: &Suit) -> &'static str {
+    match suit {
+        Suit::Clubs => "♣",
+        Suit::Diamonds => "♦",
+        Suit::Hearts => "♥",
+        Suit::Spades => "♠",
+    }
+}
+
+fn suit_color(suit: &Suit) -> &'static str {
+    match suit {
+        Suit::Diamonds | Suit::Hearts => "#c62828",
+        Suit::Clubs | Suit::Spades => "black",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use badam_sat::games::{BadamSat, Transition};
+    use card_deck::standard_deck::{Card, Rank, StandardDeckBuilder, Suit};
+
+    use super::board_svg;
+
+    #[test]
+    fn test_board_shows_stack_ends() {
+        let deck = StandardDeckBuilder::new().build();
+        let mut game = BadamSat::from_hands(vec![deck.iter().cloned().collect()], 1).unwrap();
+        let cards = [
+            (Suit::Hearts, 7),
+            (Suit::Hearts, 6),
+            (Suit::Hearts, 5),
+            (Suit::Hearts, 8),
+            (Suit::Spades, 7),
+        ];
+        for (suit, rank) in cards {
+            let card = Card::new_normal(suit, Rank::new(rank));
+            game.update(Transition::Play { player: 0, card }).unwrap();
+        }
+
+        let svg = board_svg(game.playing_area());
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert!(svg.contains(">8</text>") && svg.contains(">5</text>"));
+        assert_eq!(svg.matches(">7</text>").count(), 2);
+        // clubs and diamonds are empty
+        assert_eq!(svg.matches("stroke-dasharray").count(), 2);
+    }
+}
@@ -1,36 +1,138 @@
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use badam_sat::games::{BadamSat, PlayingArea, Transition};
-use card_deck::standard_deck::Card;
+use badam_sat::games::{
+    BadamSat, BadamSatBuilder, Direction, EndReason, InvalidTransition, LeftoverPolicy,
+    PlayingArea, StackState, Transition,
+};
+use badam_sat_protocol::{Action, GameState, MyTurn};
+use card_deck::standard_deck::{Card, Suit};
 use pasetors::claims::Claims;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::{
     sync::{mpsc, oneshot},
-    time::timeout,
+    time::{timeout_at, Instant},
 };
+use uuid::Uuid;
 
-use crate::{errors::Error, server::ServerRoomMessage};
+use crate::{
+    errors::Error,
+    server::{to_hex, ServerRoomMessage},
+    stats::StatsStore,
+};
+
+/// How long the host has to use a confirmation for a destructive action.
+const CONFIRMATION_LIFETIME: Duration = Duration::from_secs(10);
 
 #[derive(Debug)]
 pub struct Room {
     joined_players: usize,
+    ready: Vec<bool>,
+    kicked: HashMap<usize, bool>,
     game: BadamSat,
     max_player_count: usize,
     last_move: Option<Action>,
+    seed: [u8; 32],
+    config: RoomConfig,
+    turn_duration: Option<Duration>,
+    turn_warning: Duration,
+    turn_started: Instant,
+    turn_warned: bool,
+    last_activity: Instant,
+    started_at: Option<Instant>,
+    max_game_duration: Option<Duration>,
+    events: Vec<RoomEvent>,
+    last_plays: HashMap<usize, (Uuid, Result<PlayOutcome, Error>)>,
+    illegal_moves: HashMap<usize, usize>,
+    confirmation: Option<(Uuid, Instant)>,
+    player_keys: HashMap<usize, Uuid>,
+    password: Option<RoomPassword>,
+    stats: Arc<Mutex<StatsStore>>,
+}
+
+/// Password of a private room, kept only as a salted hash.
+#[derive(Debug, Clone)]
+pub struct RoomPassword {
+    salt: [u8; 16],
+    hash: [u8; 32],
+}
+
+impl RoomPassword {
+    pub fn new(password: &str) -> Self {
+        let salt = rand::random();
+        RoomPassword {
+            salt,
+            hash: Self::hash(&salt, password),
+        }
+    }
+
+    fn matches(&self, password: &str) -> bool {
+        Self::hash(&self.salt, password) == self.hash
+    }
+
+    fn hash(salt: &[u8; 16], password: &str) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(salt)
+            .chain_update(password)
+            .finalize()
+            .into()
+    }
 }
 
 impl Room {
-    /// Create a new room that can accommodate given amount of players and card
-    /// decks.
-    pub fn spawn(players: usize, decks: usize, receiver: mpsc::Receiver<ServerRoomMessage>) {
-        let game = BadamSat::with_player_and_deck_capacity(players, decks);
-        let room = Room {
+    /// Create a new room with the given `config`, shuffling the cards using
+    /// the `seed`. The game is ended once it has been going on for
+    /// `max_game_duration`. Results of the players who joined with a player
+    /// key are recorded in the `stats`. Only players who know the `password`
+    /// can join, if the room has one.
+    pub fn spawn(
+        config: RoomConfig,
+        seed: [u8; 32],
+        password: Option<RoomPassword>,
+        max_game_duration: Option<Duration>,
+        stats: Arc<Mutex<StatsStore>>,
+        receiver: mpsc::Receiver<ServerRoomMessage>,
+    ) {
+        let room = Room::new(config, seed, password, max_game_duration, stats);
+        tokio::spawn(room.run(receiver));
+    }
+
+    fn new(
+        config: RoomConfig,
+        seed: [u8; 32],
+        password: Option<RoomPassword>,
+        max_game_duration: Option<Duration>,
+        stats: Arc<Mutex<StatsStore>>,
+    ) -> Room {
+        let game = config.build_game(seed);
+        Room {
             joined_players: 0,
+            ready: Vec::new(),
+            kicked: HashMap::new(),
             game,
-            max_player_count: players,
+            max_player_count: config.players,
             last_move: None,
-        };
-        tokio::spawn(room.run(receiver));
+            seed,
+            turn_duration: config.turn_seconds.map(Duration::from_secs),
+            turn_warning: Duration::from_secs(config.turn_warning_seconds),
+            config,
+            turn_started: Instant::now(),
+            turn_warned: false,
+            last_activity: Instant::now(),
+            started_at: None,
+            max_game_duration,
+            events: Vec::new(),
+            last_plays: HashMap::new(),
+            illegal_moves: HashMap::new(),
+            confirmation: None,
+            player_keys: HashMap::new(),
+            password,
+            stats,
+        }
     }
 
     async fn run(mut self, mut receiver: mpsc::Receiver<ServerRoomMessage>) {
@@ -38,39 +140,283 @@ impl Room {
             responder.send(msg).is_ok()
         }
 
-        while let Ok(Some(msg)) = timeout(Duration::from_secs(5 * 60), receiver.recv()).await {
+        let idle_timeout = Duration::from_secs(5 * 60);
+        let mut idle_deadline = Instant::now() + idle_timeout;
+        loop {
+            let wakeup = [self.turn_timer_deadline(), self.game_deadline()]
+                .into_iter()
+                .flatten()
+                .fold(idle_deadline, Instant::min);
+            let msg = match timeout_at(wakeup, receiver.recv()).await {
+                Ok(Some(msg)) => msg,
+                Ok(None) => break,
+                Err(_) if Instant::now() >= idle_deadline => {
+                    log::info!("no client activity for 5 minutes, exiting room");
+                    break;
+                }
+                Err(_) => {
+                    self.tick_game_timer();
+                    self.tick_turn_timer();
+                    continue;
+                }
+            };
+            if !matches!(
+                msg,
+                ServerRoomMessage::Heartbeat(_) | ServerRoomMessage::Activity(_)
+            ) {
+                self.last_activity = Instant::now();
+                idle_deadline = self.last_activity + idle_timeout;
+            }
             let success = match msg {
-                ServerRoomMessage::AddPlayer(responder) => respond(responder, self.join()),
+                ServerRoomMessage::AddPlayer {
+                    player_key,
+                    password,
+                    responder,
+                } => respond(responder, self.join(player_key, password.as_deref())),
                 ServerRoomMessage::Play {
                     action,
                     player,
+                    idempotency_key,
+                    responder,
+                } => respond(responder, self.play(action, player, idempotency_key)),
+                ServerRoomMessage::SetReady {
+                    player,
+                    ready,
+                    responder,
+                } => respond(responder, self.set_ready(player, ready)),
+                ServerRoomMessage::Start { player, responder } => {
+                    respond(responder, self.start(player))
+                }
+                ServerRoomMessage::Confirm { player, responder } => {
+                    respond(responder, self.confirmation(player))
+                }
+                ServerRoomMessage::Kick {
+                    player,
+                    kicked,
+                    discard_cards,
+                    confirmation,
+                    responder,
+                } => respond(
+                    responder,
+                    self.kick(player, kicked, discard_cards, confirmation),
+                ),
+                ServerRoomMessage::Reconfigure {
+                    player,
+                    players,
+                    decks,
+                    teams,
+                    confirmation,
                     responder,
-                } => respond(responder, self.play(action, player)),
+                } => respond(
+                    responder,
+                    self.reconfigure(player, players, decks, teams, confirmation),
+                ),
                 ServerRoomMessage::GameOver(responder) => respond(responder, self.is_game_over()),
                 ServerRoomMessage::LastMove(responder) => respond(responder, self.last_move),
                 ServerRoomMessage::Hand { player, responder } => {
                     respond(responder, self.hand_of_player(player))
                 }
+                ServerRoomMessage::MoveCount { player, responder } => {
+                    respond(responder, self.move_count(player))
+                }
+                ServerRoomMessage::MyTurn { player, responder } => {
+                    respond(responder, self.my_turn(player))
+                }
+                ServerRoomMessage::Hint { player, responder } => {
+                    respond(responder, self.hint(player))
+                }
+                ServerRoomMessage::DealtCards { player, responder } => {
+                    respond(responder, self.dealt_cards(player))
+                }
+                ServerRoomMessage::DealSequence(responder) => {
+                    respond(responder, self.game.deal_sequence())
+                }
+                ServerRoomMessage::Placement {
+                    player,
+                    card,
+                    responder,
+                } => respond(responder, self.placement(player, card)),
+                #[cfg(feature = "debug")]
+                ServerRoomMessage::Debug(responder) => respond(responder, format!("{self:#?}")),
+                // receiving the message already reset the idle timer
+                ServerRoomMessage::Ping(responder) => respond(responder, ()),
+                ServerRoomMessage::Heartbeat(responder) => respond(responder, ()),
+                ServerRoomMessage::Activity(responder) => respond(responder, self.activity()),
+                ServerRoomMessage::ValidActions(responder) => {
+                    respond(responder, self.valid_actions())
+                }
+                ServerRoomMessage::MissingCards(responder) => {
+                    respond(responder, self.playing_area().missing_cards())
+                }
+                ServerRoomMessage::CardCounts(responder) => {
+                    respond(responder, self.visible_card_counts())
+                }
+                ServerRoomMessage::IllegalMoves(responder) => {
+                    respond(responder, self.illegal_moves())
+                }
+                ServerRoomMessage::TurnOrder(responder) => respond(responder, self.turn_order()),
+                ServerRoomMessage::TurnTimer(responder) => respond(responder, self.turn_timer()),
+                ServerRoomMessage::Winner(responder) => respond(responder, self.winner()),
                 ServerRoomMessage::GameState(responder) => respond(responder, self.game_state()),
+                ServerRoomMessage::Seed(responder) => respond(responder, self.seed()),
+                ServerRoomMessage::Export(responder) => respond(responder, self.export()),
+                ServerRoomMessage::Events {
+                    since,
+                    catch_up,
+                    responder,
+                } => respond(responder, self.events_since(since, catch_up)),
             };
             if !success {
                 log::warn!("sending data to server from room failed, exiting");
                 break; // The server dropped?? Need to figure out how to handle this better. Logging?
             }
         }
-        log::info!("no client activity for 5 minutes, exiting room");
     }
 
-    /// Try to join the room.
-    pub fn join(&mut self) -> Result<Claims, Error> {
+    /// Get the time at which the turn timer next needs attention, if the room
+    /// has a turn timer and the game is in play.
+    fn turn_timer_deadline(&self) -> Option<Instant> {
+        let turn_duration = self.turn_duration?;
+        self.game.current_player()?;
+        if self.turn_warned {
+            Some(self.turn_started + turn_duration)
+        } else {
+            Some(self.turn_started + turn_duration.saturating_sub(self.turn_warning))
+        }
+    }
+
+    /// Get the time at which the game runs out of time, if the game is in
+    /// play and has a maximum duration.
+    fn game_deadline(&self) -> Option<Instant> {
+        self.game.current_player()?;
+        Some(self.started_at? + self.max_game_duration?)
+    }
+
+    /// End the game if it has run out of time.
+    fn tick_game_timer(&mut self) {
+        let Some(deadline) = self.game_deadline() else {
+            return;
+        };
+        if Instant::now() >= deadline {
+            log::info!("game ran out of time, ending it");
+            self.game.end_game().unwrap();
+            self.record_game_finished();
+        }
+    }
+
+    /// Warn the current player or skip their turn, depending on how much time
+    /// they have left.
+    fn tick_turn_timer(&mut self) {
+        let (Some(turn_duration), Some(player)) = (self.turn_duration, self.game.current_player())
+        else {
+            return;
+        };
+        let expiry = self.turn_started + turn_duration;
+        let warn_at = self.turn_started + turn_duration.saturating_sub(self.turn_warning);
+        let now = Instant::now();
+        if now >= expiry {
+            log::info!("player {player} ran out of time, skipping their turn");
+            self.events.push(RoomEvent::TurnTimedOut { player });
+            self.skip_turn();
+            self.start_turn();
+        } else if !self.turn_warned && now >= warn_at {
+            self.turn_warned = true;
+            self.events.push(RoomEvent::TurnWarning {
+                player,
+                seconds_left: (expiry - now).as_secs_f32().round() as u64,
+            });
+        }
+    }
+
+    /// Restart the turn timer for the next turn, passing over any kicked
+    /// players.
+    fn start_turn(&mut self) {
+        while let Some(player) = self.game.current_player() {
+            if !self.kicked.contains_key(&player) {
+                break;
+            }
+            self.game.sit_out().unwrap();
+            self.record_if_over();
+        }
+        self.turn_started = Instant::now();
+        self.turn_warned = false;
+    }
+
+    /// Skip the current player's turn. Skipping a player who has nothing to
+    /// play can end the game in a stalemate, which is then recorded.
+    fn skip_turn(&mut self) {
+        self.game.skip_turn().unwrap();
+        self.record_if_over();
+    }
+
+    fn record_if_over(&mut self) {
+        if self.is_game_over() {
+            self.record_game_finished();
+        }
+    }
+
+    /// Get the events that happened in the room starting from the index
+    /// `since`, along with the index to continue from.
+    ///
+    /// With `catch_up`, the events are preceded by the moves played so far, so
+    /// that a client joining late can rebuild the game.
+    pub fn events_since(&self, since: usize, catch_up: bool) -> (Vec<RoomEvent>, usize) {
+        let since = since.min(self.events.len());
+        let mut events = if catch_up {
+            self.move_events()
+        } else {
+            Vec::new()
+        };
+        events.extend_from_slice(&self.events[since..]);
+        (events, self.events.len())
+    }
+
+    /// Reconstruct the moves played so far from the game history as events.
+    fn move_events(&self) -> Vec<RoomEvent> {
+        self.game
+            .history()
+            .iter()
+            .filter_map(|transition| match *transition {
+                Transition::DealCards | Transition::EndGame | Transition::Forfeit { .. } => None,
+                Transition::Play { player, card } => Some(RoomEvent::CardPlayed { player, card }),
+                Transition::PlayJoker { player, substitute } => {
+                    Some(RoomEvent::JokerPlayed { player, substitute })
+                }
+                Transition::Pass { player }
+                | Transition::SkipTurn { player }
+                | Transition::SitOut { player } => Some(RoomEvent::Passed { player }),
+                Transition::Draw { player } => Some(RoomEvent::CardDrawn { player }),
+            })
+            .collect()
+    }
+
+    /// Try to join the room, as the player identified by the `player_key` if
+    /// one is given.
+    pub fn join(
+        &mut self,
+        player_key: Option<Uuid>,
+        password: Option<&str>,
+    ) -> Result<Claims, Error> {
+        if let Some(expected) = &self.password {
+            if !password.is_some_and(|password| expected.matches(password)) {
+                return Err(Error::WrongPassword);
+            }
+        }
         if self.is_full() {
             return Err(Error::RoomFull);
         }
         let mut claim = Claims::new().unwrap();
         claim.subject(&self.joined_players.to_string()).unwrap();
+        if let Some(player_key) = player_key {
+            claim
+                .add_additional("player_key", serde_json::to_value(player_key).unwrap())
+                .unwrap();
+            self.player_keys.insert(self.joined_players, player_key);
+        }
         self.joined_players += 1;
-        if self.is_full() {
-            self.game.update(Transition::DealCards).unwrap();
+        self.ready.push(false);
+        if self.is_full() && !self.config.manual_start {
+            self.deal();
         }
         Ok(claim)
     }
@@ -80,23 +426,259 @@ impl Room {
         self.max_player_count == self.joined_players
     }
 
+    /// Check whether the cards have been dealt.
+    pub fn has_started(&self) -> bool {
+        !self.game.history().is_empty()
+    }
+
+    /// Mark the `player` as ready, or not, to start the game.
+    pub fn set_ready(&mut self, player: usize, ready: bool) -> Result<(), Error> {
+        let flag = self.ready.get_mut(player).ok_or(Error::InvalidPlayerId)?;
+        *flag = ready;
+        Ok(())
+    }
+
+    /// Deal the cards on behalf of the `player`.
+    ///
+    /// Only the host, the first player to join, can start the game, and only
+    /// once the room is full and every player is ready.
+    pub fn start(&mut self, player: usize) -> Result<(), Error> {
+        if player != 0 {
+            return Err(Error::NotHost);
+        }
+        let all_ready = self
+            .ready
+            .iter()
+            .enumerate()
+            .all(|(player, &ready)| ready || self.kicked.contains_key(&player));
+        if !self.is_full() || !all_ready {
+            return Err(Error::PlayersNotReady);
+        }
+        if self.has_started() {
+            return Err(Error::InvalidMove(InvalidTransition::NotAllowed));
+        }
+        self.deal();
+        Ok(())
+    }
+
+    /// Issue a confirmation that the host `player` needs for their next
+    /// destructive action, replacing any earlier one.
+    pub fn confirmation(&mut self, player: usize) -> Result<Confirmation, Error> {
+        if player != 0 {
+            return Err(Error::NotHost);
+        }
+        let nonce = Uuid::new_v4();
+        self.confirmation = Some((nonce, Instant::now()));
+        Ok(Confirmation {
+            nonce,
+            expires_in_seconds: CONFIRMATION_LIFETIME.as_secs(),
+        })
+    }
+
+    /// Check the `nonce` against the issued confirmation. A confirmation can
+    /// be used only once, even by a failed action, so that a repeated click
+    /// does not act twice.
+    fn use_confirmation(&mut self, nonce: Uuid) -> Result<(), Error> {
+        match self.confirmation.take() {
+            Some((issued, at)) if issued == nonce && at.elapsed() < CONFIRMATION_LIFETIME => Ok(()),
+            _ => Err(Error::InvalidConfirmation),
+        }
+    }
+
+    /// Remove the `kicked` player from the game on behalf of the `player`.
+    ///
+    /// Only the host can kick, and they cannot kick themselves. The turns of
+    /// the kicked player are passed from then on. Their cards still count
+    /// towards their score, but if `discard_cards` is set they are left out of
+    /// the finishing order. Once everyone but the host is kicked, the game
+    /// ends with the host winning by forfeit.
+    pub fn kick(
+        &mut self,
+        player: usize,
+        kicked: usize,
+        discard_cards: bool,
+        confirmation: Uuid,
+    ) -> Result<(), Error> {
+        if player != 0 {
+            return Err(Error::NotHost);
+        }
+        self.use_confirmation(confirmation)?;
+        if kicked == player || kicked >= self.joined_players {
+            return Err(Error::InvalidPlayerId);
+        }
+        self.kicked.insert(kicked, discard_cards);
+        self.events.push(RoomEvent::PlayerKicked { player: kicked });
+        if self.game.current_player().is_some()
+            && (1..self.joined_players).all(|player| self.kicked.contains_key(&player))
+        {
+            self.game.forfeit(player).unwrap();
+            self.record_game_finished();
+        } else if self.game.current_player() == Some(kicked) {
+            self.start_turn();
+        }
+        Ok(())
+    }
+
+    /// Change the number of `players`, the `decks` and the `teams` of the room
+    /// on behalf of the `player`, leaving out the settings that stay the same.
+    ///
+    /// Only the host can change the settings, and only before the cards are
+    /// dealt. Once other players have joined, only the number of players can
+    /// change, and not below the number who joined. The game is set up again
+    /// with the new settings, keeping the seats of the joined players, and the
+    /// cards are dealt right away if that fills the room. A room with teams
+    /// needs new `teams` covering every seat when its number of players
+    /// changes.
+    pub fn reconfigure(
+        &mut self,
+        player: usize,
+        players: Option<usize>,
+        decks: Option<usize>,
+        teams: Option<Vec<usize>>,
+        confirmation: Uuid,
+    ) -> Result<(), Error> {
+        if player != 0 {
+            return Err(Error::NotHost);
+        }
+        self.use_confirmation(confirmation)?;
+        if self.has_started() || (self.joined_players > 1 && decks.is_some()) {
+            return Err(Error::RoomLocked);
+        }
+        let mut config = self.config.clone();
+        config.players = players.unwrap_or(config.players);
+        config.decks = decks.unwrap_or(config.decks);
+        if teams.is_some() {
+            config.teams = teams;
+        }
+        config.validate()?;
+        if config.players < self.joined_players {
+            return Err(Error::InvalidRoomConfig);
+        }
+        self.game = config.build_game(self.seed);
+        self.max_player_count = config.players;
+        self.events.push(RoomEvent::RoomReconfigured {
+            players: config.players,
+            decks: config.decks,
+        });
+        self.config = config;
+        if self.is_full() && !self.config.manual_start {
+            self.deal();
+        }
+        Ok(())
+    }
+
+    fn deal(&mut self) {
+        self.game.update(Transition::DealCards).unwrap();
+        self.started_at = Some(Instant::now());
+        self.start_turn();
+    }
+
     /// Attempt to play a card.
-    pub fn play(&mut self, action: Action, player: usize) -> Result<(), Error> {
-        if !self.is_full() {
+    ///
+    /// If the `idempotency_key` is the same as that of the `player`'s previous
+    /// play, the previous result is returned without playing again.
+    pub fn play(
+        &mut self,
+        action: Action,
+        player: usize,
+        idempotency_key: Option<Uuid>,
+    ) -> Result<PlayOutcome, Error> {
+        let Some(key) = idempotency_key else {
+            return self.apply_play(action, player);
+        };
+        if let Some((last_key, result)) = self.last_plays.get(&player) {
+            if last_key == &key {
+                return result.clone();
+            }
+        }
+        let result = self.apply_play(action, player);
+        self.last_plays.insert(player, (key, result.clone()));
+        result
+    }
+
+    fn apply_play(&mut self, action: Action, player: usize) -> Result<PlayOutcome, Error> {
+        if !self.has_started() {
             return Err(Error::TooEarly);
         }
         let transition = match action {
             Action::Play(card) => Transition::Play { player, card },
             Action::Pass => Transition::Pass { player },
         };
+        let forced_pass =
+            matches!(action, Action::Pass) && self.game.playable_cards(player).is_empty();
         match self.game.update(transition) {
             Ok(_) => {
                 if matches!(action, Action::Play(..)) {
                     self.last_move = Some(action);
                 }
-                Ok(())
+                metrics::increment_counter!("plays_total");
+                if self.is_game_over() {
+                    self.record_game_finished();
+                }
+                self.start_turn();
+                Ok(PlayOutcome {
+                    next_player: self.game.current_player(),
+                    forced_pass,
+                })
+            }
+            Err(err) => {
+                let error = if self.game.current_player().is_some_and(|p| p != player) {
+                    Error::NotYourTurn
+                } else {
+                    Error::InvalidMove(err)
+                };
+                self.penalize_illegal_move(player);
+                Err(error)
+            }
+        }
+    }
+
+    /// Count an illegal move attempt by the `player`, and penalize them every
+    /// time their attempts reach a multiple of the room's limit.
+    ///
+    /// A player cannot have their turn skipped while it is not their turn, so
+    /// they are warned instead.
+    fn penalize_illegal_move(&mut self, player: usize) {
+        let attempts = self.illegal_moves.entry(player).or_default();
+        *attempts += 1;
+        let attempts = *attempts;
+        if self.config.illegal_move_penalty == IllegalMovePenalty::None
+            || !attempts.is_multiple_of(self.config.illegal_move_limit)
+        {
+            return;
+        }
+        if self.config.illegal_move_penalty == IllegalMovePenalty::SkipTurn
+            && self.game.current_player() == Some(player)
+        {
+            log::info!("player {player} made {attempts} illegal moves, skipping their turn");
+            self.events
+                .push(RoomEvent::IllegalMoveSkip { player, attempts });
+            self.skip_turn();
+            self.start_turn();
+        } else {
+            self.events
+                .push(RoomEvent::IllegalMoveWarning { player, attempts });
+        }
+    }
+
+    /// Get the number of illegal moves each player who has joined attempted.
+    pub fn illegal_moves(&self) -> Vec<usize> {
+        (0..self.joined_players)
+            .map(|player| self.illegal_moves.get(&player).copied().unwrap_or(0))
+            .collect()
+    }
+
+    fn record_game_finished(&mut self) {
+        if let (Some(winner), Some(reason)) = (self.game.winner(), self.game.end_reason()) {
+            self.events.push(RoomEvent::GameOver { winner, reason });
+            let mut stats = self.stats.lock().unwrap();
+            for (player, player_key) in &self.player_keys {
+                stats.record(*player_key, *player == winner);
             }
-            Err(_) => Err(Error::InvalidMove),
+        }
+        metrics::increment_counter!("games_finished_total");
+        if let Some(started_at) = self.started_at {
+            metrics::histogram!("game_duration_seconds", started_at.elapsed());
         }
     }
 
@@ -113,37 +695,813 @@ impl Room {
             .ok_or(Error::InvalidPlayerId)
     }
 
+    /// Get the `player`'s hand and the cards they can play this turn.
+    pub fn my_turn(&self, player: usize) -> Result<MyTurn, Error> {
+        let hand = self.hand_of_player(player)?;
+        Ok(MyTurn {
+            hand,
+            playable: self.game.playable_cards(player),
+            is_my_turn: self.game.current_player() == Some(player),
+            can_pass: self.game.can_pass(player),
+            current_player: self.game.current_player(),
+        })
+    }
+
+    /// Suggest a card for the `player` to play, if it is their turn and they
+    /// have one.
+    pub fn hint(&self, player: usize) -> Result<Option<Card>, Error> {
+        if player >= self.joined_players {
+            return Err(Error::InvalidPlayerId);
+        }
+        Ok(self.game.hint(player))
+    }
+
+    /// Get the cards dealt to the `player`, in the order they were dealt.
+    pub fn dealt_cards(&self, player: usize) -> Result<Vec<Card>, Error> {
+        if player >= self.joined_players {
+            return Err(Error::InvalidPlayerId);
+        }
+        self.game.dealt_cards(player).ok_or(Error::InvalidPlayerId)
+    }
+
+    /// Get the number of different cards the `player` can play this turn,
+    /// which is zero when it is not their turn.
+    pub fn move_count(&self, player: usize) -> Result<usize, Error> {
+        if player >= self.joined_players {
+            return Err(Error::InvalidPlayerId);
+        }
+        Ok(self.game.playable_cards(player).len())
+    }
+
+    /// Get the index of the stack that the `player`'s `card` would be placed
+    /// on, if it can be played right now.
+    pub fn placement(&self, player: usize, card: Card) -> Result<Option<usize>, Error> {
+        if !self.hand_of_player(player)?.contains(&card) {
+            return Err(Error::InvalidCard);
+        }
+        Ok(self.playing_area().placement_for(card))
+    }
+
     /// Check whether the game is over.
     pub fn is_game_over(&self) -> bool {
         self.game.winner().is_some()
     }
 
+    /// Get the time of the last client activity in the room, if the game is
+    /// in progress.
+    ///
+    /// Password protected rooms are private, so they never report activity and
+    /// are never picked as the active room.
+    pub fn activity(&self) -> Option<Instant> {
+        (self.password.is_none() && self.has_started() && !self.is_game_over())
+            .then_some(self.last_activity)
+    }
+
+    /// Reveal the seed used for shuffling the cards, once the game is over.
+    ///
+    /// Only seeded rooms reveal their seed through this method.
+    pub fn seed(&self) -> Result<[u8; 32], Error> {
+        if !self.config.seeded {
+            Err(Error::NoSeed)
+        } else if self.is_game_over() {
+            Ok(self.seed)
+        } else {
+            Err(Error::GameNotOver)
+        }
+    }
+
+    /// Export the completed game.
+    pub fn export(&self) -> Result<GameExport, Error> {
+        let mut finishing_order = self.game.finishing_order().ok_or(Error::GameNotOver)?;
+        finishing_order.retain(|player| self.kicked.get(player) != Some(&true));
+        let mut kicked: Vec<usize> = self.kicked.keys().copied().collect();
+        kicked.sort_unstable();
+        let scores = self.game.scores();
+        Ok(GameExport {
+            schema_version: crate::SCHEMA_VERSION,
+            config: self.config.clone(),
+            seed: to_hex(&self.seed),
+            moves: self.game.history().to_vec(),
+            starting_hand_sizes: self.game.starting_hand_sizes().to_vec(),
+            team_results: self.team_results(),
+            finishing_order,
+            scores,
+            kicked,
+            end_reason: self.game.end_reason().ok_or(Error::GameNotOver)?,
+        })
+    }
+
+    /// Get the cards that the current player could play, judging only by the
+    /// playing area so that their hand is not revealed.
+    pub fn valid_actions(&self) -> ValidActions {
+        let player = self.game.current_player();
+        ValidActions {
+            player,
+            cards: player.map_or_else(Vec::new, |_| self.game.open_cards()),
+        }
+    }
+
+    /// Get the seats in the order the turns go around the table.
+    pub fn turn_order(&self) -> TurnOrder {
+        let direction = self.game.direction();
+        let order = match direction {
+            Direction::Clockwise => (0..self.max_player_count).collect(),
+            Direction::Counterclockwise => (0..self.max_player_count).rev().collect(),
+        };
+        TurnOrder {
+            order,
+            current: self.game.current_player(),
+            direction,
+        }
+    }
+
+    /// Get the time left on the current turn, if the room has a turn timer
+    /// and the game is in play.
+    pub fn turn_timer(&self) -> Option<TurnTimer> {
+        let turn_duration = self.turn_duration?;
+        let player = self.game.current_player()?;
+        let deadline = self.turn_started + turn_duration;
+        let server_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Some(TurnTimer {
+            player,
+            seconds_left: deadline.saturating_duration_since(Instant::now()).as_secs(),
+            server_time: server_time.as_millis() as u64,
+        })
+    }
+
+    /// Get the winner of the game, if it is over.
+    pub fn winner(&self) -> Winner {
+        Winner {
+            winner: self.game.winner(),
+            end_reason: self.game.end_reason(),
+            team_results: self.is_game_over().then(|| self.team_results()).flatten(),
+        }
+    }
+
+    /// Add up the scores of the players by their teams, if the room has any.
+    fn team_results(&self) -> Option<TeamResults> {
+        let teams = self.config.teams.as_ref()?;
+        Some(TeamResults::new(teams, &self.game.scores()))
+    }
+
+    /// Get the number of cards in the hand of each player who has joined.
+    pub fn card_counts(&self) -> Vec<usize> {
+        (0..self.joined_players)
+            .map(|player| self.game.hand_len(player).unwrap())
+            .collect()
+    }
+
+    /// Get the number of cards in the hand of each player who has joined,
+    /// unless the room hides them.
+    pub fn visible_card_counts(&self) -> Option<Vec<usize>> {
+        (!self.config.hide_counts).then(|| self.card_counts())
+    }
+
+    /// Get the players who are out so far, or the full finishing order once
+    /// the game is over. Rooms that hide card counts only reveal it once the
+    /// game is over, as it would show who has no cards left.
+    pub fn ranking(&self) -> Vec<usize> {
+        if let Some(order) = self.game.finishing_order() {
+            order
+        } else if self.config.hide_counts {
+            Vec::new()
+        } else {
+            self.game.out_of_cards().to_vec()
+        }
+    }
+
     pub fn game_state(&self) -> GameState {
         GameState {
             playing_area: self.playing_area().clone(),
-            card_counts: (0..self.joined_players)
-                .map(|player| self.game.hand_len(player).unwrap())
-                .collect(),
+            card_counts: self.visible_card_counts(),
+            pass_counts: self.game.pass_counts().to_vec(),
+            finished: self.is_game_over(),
+            ranking: self.ranking(),
+            current_player: self.game.current_player(),
+            ready: self.ready.clone(),
+            theme: self.config.theme.clone(),
         }
     }
 }
 
-/// An action that a player can take; either play a card or pass their turn.
-#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
-pub enum Action {
-    Play(Card),
-    Pass,
+/// Cosmetic themes that a room can be created with. The first one is the
+/// default.
+pub const THEMES: &[&str] = &["classic", "dark", "high_contrast"];
+
+fn default_theme() -> String {
+    THEMES[0].to_owned()
+}
+
+/// Settings chosen when creating a room.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct RoomConfig {
+    pub players: usize,
+    pub decks: usize,
+    /// Whether to shuffle with a seed that is revealed after the game.
+    #[serde(default)]
+    pub seeded: bool,
+    /// Card back and color scheme for the clients to render the room with.
+    #[serde(default = "default_theme")]
+    pub theme: String,
+    /// Time limit for each turn, after which the turn is skipped.
+    #[serde(default)]
+    pub turn_seconds: Option<u64>,
+    /// How long before the end of a turn to warn the player.
+    #[serde(default = "default_turn_warning_seconds")]
+    pub turn_warning_seconds: u64,
+    /// Wait for the host to start the game instead of dealing as soon as the
+    /// room is full.
+    #[serde(default)]
+    pub manual_start: bool,
+    /// Let players pass even when they could play a card.
+    #[serde(default)]
+    pub allow_voluntary_pass: bool,
+    /// How many times each player may pass when they could play a card.
+    #[serde(default)]
+    pub max_passes: Option<usize>,
+    /// Only allow player and deck counts that give everyone the same number
+    /// of cards.
+    #[serde(default)]
+    pub fair: bool,
+    /// Points taken off the score of whoever completes a suit.
+    #[serde(default)]
+    pub completion_bonus: usize,
+    /// Which seats get the extra cards when the deal is uneven.
+    #[serde(default)]
+    pub leftover_policy: LeftoverPolicy,
+    /// Start the game with every seven already on the playing area.
+    #[serde(default)]
+    pub autoseed_sevens: bool,
+    /// Let an 8 reverse the direction of the turns and a 6 skip a player.
+    #[serde(default)]
+    pub special_cards: bool,
+    /// Keep playing after the first player is out, until one is left.
+    #[serde(default)]
+    pub play_until_last: bool,
+    /// Hide how many cards each player holds, from everyone.
+    #[serde(default)]
+    pub hide_counts: bool,
+    /// Number of illegal move attempts after which a player is penalized.
+    #[serde(default = "default_illegal_move_limit")]
+    pub illegal_move_limit: usize,
+    /// How players are penalized for illegal move attempts, if at all.
+    #[serde(default)]
+    pub illegal_move_penalty: IllegalMovePenalty,
+    /// Team of each seat, numbered from 0, for scoring in partnerships.
+    #[serde(default)]
+    pub teams: Option<Vec<usize>>,
+}
+
+impl RoomConfig {
+    /// Check that the settings make a playable game.
+    pub fn validate(&self) -> Result<(), Error> {
+        if !THEMES.contains(&self.theme.as_str()) {
+            return Err(Error::InvalidTheme);
+        }
+        let builder = BadamSatBuilder::new(self.players, self.decks);
+        if self.players == 0
+            || !builder.has_opening_card()
+            || (self.fair && !builder.deals_evenly())
+            || self.illegal_move_limit == 0
+        {
+            return Err(Error::InvalidRoomConfig);
+        }
+        if !self.has_valid_teams() {
+            return Err(Error::InvalidTeams);
+        }
+        Ok(())
+    }
+
+    /// Set up the game with these settings, shuffling with the `seed`.
+    fn build_game(&self, seed: [u8; 32]) -> BadamSat {
+        BadamSatBuilder::new(self.players, self.decks)
+            .allow_voluntary_pass(self.allow_voluntary_pass)
+            .max_passes(self.max_passes)
+            .completion_bonus(self.completion_bonus)
+            .leftover_policy(self.leftover_policy)
+            .autoseed_sevens(self.autoseed_sevens)
+            .special_cards(self.special_cards)
+            .play_until_last(self.play_until_last)
+            .seed(seed)
+            .build()
+    }
+
+    /// Check that the team assignment, if any, covers every seat and leaves
+    /// no team without players.
+    pub fn has_valid_teams(&self) -> bool {
+        let Some(teams) = &self.teams else {
+            return true;
+        };
+        let team_count = teams.iter().max().map_or(0, |team| team + 1);
+        teams.len() == self.players && (0..team_count).all(|team| teams.contains(&team))
+    }
+}
+
+fn default_turn_warning_seconds() -> u64 {
+    10
+}
+
+fn default_illegal_move_limit() -> usize {
+    3
+}
+
+/// Penalty for a player who keeps attempting illegal moves, which could be
+/// used to probe what the other players hold.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IllegalMovePenalty {
+    /// Only count the attempts.
+    #[default]
+    None,
+    /// Warn everyone in the room about the player.
+    Warn,
+    /// Skip the player's turn.
+    SkipTurn,
+}
+
+/// A completed game with everything needed to replay it.
+#[derive(Debug, Clone, Serialize)]
+pub struct GameExport {
+    /// Version of this format, see [`crate::SCHEMA_VERSION`].
+    schema_version: u32,
+    config: RoomConfig,
+    /// Hex encoded seed the cards were shuffled with.
+    seed: String,
+    /// Number of cards each player was dealt by the first move.
+    starting_hand_sizes: Vec<usize>,
+    moves: Vec<Transition>,
+    finishing_order: Vec<usize>,
+    scores: Vec<isize>,
+    kicked: Vec<usize>,
+    end_reason: EndReason,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    team_results: Option<TeamResults>,
+}
+
+/// Combined results of the teams in a partnership game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TeamResults {
+    /// Teams in order of lowest combined score. Ties keep the team order.
+    finishing_order: Vec<usize>,
+    /// Combined score of each team's players.
+    scores: Vec<isize>,
+}
+
+impl TeamResults {
+    /// Add up the players' `scores` by their `teams`.
+    fn new(teams: &[usize], scores: &[isize]) -> Self {
+        let mut team_scores = vec![0; teams.iter().max().map_or(0, |team| team + 1)];
+        for (team, score) in teams.iter().zip(scores) {
+            team_scores[*team] += score;
+        }
+        let mut finishing_order: Vec<usize> = (0..team_scores.len()).collect();
+        finishing_order.sort_by_key(|team| team_scores[*team]);
+        TeamResults {
+            finishing_order,
+            scores: team_scores,
+        }
+    }
+}
+
+/// Notable things that happen in a room, for the clients to react to.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event")]
+pub enum RoomEvent {
+    /// The `player`'s turn is about to time out.
+    TurnWarning { player: usize, seconds_left: u64 },
+    /// The `player`'s turn timed out and was skipped.
+    TurnTimedOut { player: usize },
+    /// The game ended for the `reason` and the `winner` was decided.
+    GameOver { winner: usize, reason: EndReason },
+    /// The `player` made another `attempts` illegal moves.
+    IllegalMoveWarning { player: usize, attempts: usize },
+    /// The `player`'s turn was skipped for making `attempts` illegal moves.
+    IllegalMoveSkip { player: usize, attempts: usize },
+    /// The host removed the `player` from the game.
+    PlayerKicked { player: usize },
+    /// The host changed the number of `players` and `decks` of the room.
+    RoomReconfigured { players: usize, decks: usize },
+    /// The `player` played the `card`. Only sent when catching up.
+    CardPlayed { player: usize, card: Card },
+    /// The `player` played a joker in place of the `substitute`. Only sent
+    /// when catching up.
+    JokerPlayed { player: usize, substitute: Card },
+    /// The `player` passed or their turn was skipped. Only sent when catching
+    /// up.
+    Passed { player: usize },
+    /// The `player` drew a card from the stock. Only sent when catching up.
+    CardDrawn { player: usize },
+}
+
+/// Result of a successful play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct PlayOutcome {
+    /// Player whose turn is next, if the game is not over.
+    next_player: Option<usize>,
+    /// Whether the player passed because they had no card to play.
+    forced_pass: bool,
 }
 
-/// Winning player Id.
+/// Cards that can be played next, for spectators.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidActions {
+    /// Player whose turn it is, if the game is in play.
+    player: Option<usize>,
+    /// Cards that connect to the playing area, whoever holds them.
+    cards: Vec<Card>,
+}
+
+/// Seating of the players, for drawing the table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TurnOrder {
+    /// Seats in the order they take turns.
+    order: Vec<usize>,
+    /// Player whose turn it is, if the game is in play.
+    current: Option<usize>,
+    direction: Direction,
+}
+
+/// Countdown of the current turn, for clients to keep their clocks in sync.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TurnTimer {
+    /// Player whose turn it is.
+    player: usize,
+    seconds_left: u64,
+    /// Milliseconds since the Unix epoch on the server when this was sent.
+    server_time: u64,
+}
+
+/// Nonce the host sends along with a destructive action, to confirm it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Confirmation {
+    nonce: Uuid,
+    expires_in_seconds: u64,
+}
+
+/// Winning player Id, `None` while the game is still going on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct Winner {
-    id: usize,
+    winner: Option<usize>,
+    /// How the game ended, `None` while it is still going on.
+    end_reason: Option<EndReason>,
+    /// Combined results of the teams, once a game with teams is over.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    team_results: Option<TeamResults>,
 }
 
-/// Game state that does not reveal players' cards, so can be communicated with everyone.
+/// Terse form of [`GameState`] for clients on slow connections.
+///
+/// Each stack is a `[suit_index, low_rank, high_rank]` triple, with the suit
+/// indexed in the order of [`Suit::all_suits`] and both ranks `0` for an
+/// empty stack. Cards represented by jokers are `[suit_index, rank]` pairs.
+/// Card counts are kept as they are, since a hand can hold more cards than
+/// fit in a byte when many decks are shared by few players. The theme is left out since it never changes during a game.
+///
+/// Twenty moves into a four player game with one deck, this serializes to
+/// less than a quarter of the size of [`GameState`], and the gap widens as
+/// more stacks grow in both directions or more decks are used.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
-pub struct GameState {
-    playing_area: PlayingArea,
-    card_counts: Vec<usize>,
+pub struct CompactGameState {
+    stacks: Vec<[u8; 3]>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    jokers: Vec<[u8; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    card_counts: Option<Vec<usize>>,
+    ready: Vec<bool>,
+}
+
+impl From<GameState> for CompactGameState {
+    fn from(state: GameState) -> Self {
+        let suit_index = |suit: &Suit| {
+            Suit::all_suits()
+                .into_iter()
+                .position(|s| &s == suit)
+                .unwrap() as u8
+        };
+        let rank = |card: &Card| card.rank().unwrap().value();
+        let stacks = state.playing_area.stacks();
+        CompactGameState {
+            stacks: stacks
+                .iter()
+                .map(|stack| {
+                    let (low, high) = match stack.stack_state() {
+                        StackState::Empty => (0, 0),
+                        StackState::SevenOnly => (7, 7),
+                        StackState::LowOnly(low) => (rank(low), 7),
+                        StackState::HighOnly(high) => (7, rank(high)),
+                        StackState::LowAndHigh { low, high } => (rank(low), rank(high)),
+                    };
+                    [suit_index(stack.suit()), low, high]
+                })
+                .collect(),
+            jokers: stacks
+                .iter()
+                .flat_map(|stack| stack.jokers())
+                .map(|card| [suit_index(card.suit().unwrap()), rank(card)])
+                .collect(),
+            card_counts: state.card_counts,
+            ready: state.ready,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use badam_sat_protocol::Action;
+    use serde_json::json;
+    use tokio::{
+        sync::{mpsc, oneshot},
+        time::{self, Instant},
+    };
+
+    use badam_sat::games::EndReason;
+
+    use super::{CompactGameState, Room, RoomConfig, RoomEvent, RoomPassword, TeamResults};
+    use crate::{errors::Error, server::ServerRoomMessage};
+
+    /// Create a room with the `config` and fill it up, which deals the cards.
+    fn full_room(config: serde_json::Value) -> Room {
+        let config: RoomConfig = serde_json::from_value(config).unwrap();
+        let mut room = Room::new(config, [1; 32], None, None, Default::default());
+        while !room.is_full() {
+            room.join(None, None).unwrap();
+        }
+        room
+    }
+
+    #[test]
+    fn test_private_room_reports_no_activity() {
+        let config: RoomConfig =
+            serde_json::from_value(json!({ "players": 2, "decks": 1 })).unwrap();
+        let password = Some(RoomPassword::new("secret"));
+        let mut room = Room::new(config, [1; 32], password, None, Default::default());
+        while !room.is_full() {
+            room.join(None, Some("secret")).unwrap();
+        }
+        assert!(room.has_started());
+        assert_eq!(room.activity(), None);
+        assert!(full_room(json!({ "players": 2, "decks": 1 }))
+            .activity()
+            .is_some());
+    }
+
+    #[test]
+    fn test_team_results() {
+        let results = TeamResults::new(&[0, 1, 0, 1], &[3, 0, -2, 4]);
+        assert_eq!(results.scores, vec![1, 4]);
+        assert_eq!(results.finishing_order, vec![0, 1]);
+        // tied teams keep their order
+        let results = TeamResults::new(&[1, 0, 2], &[2, 2, 0]);
+        assert_eq!(results.scores, vec![2, 2, 0]);
+        assert_eq!(results.finishing_order, vec![2, 0, 1]);
+    }
+
+    #[test]
+    fn test_fair_rooms_need_equal_hands() {
+        let config = |players: usize| -> RoomConfig {
+            serde_json::from_value(json!({ "players": players, "decks": 1, "fair": true })).unwrap()
+        };
+        // 52 cards cannot be dealt evenly to 3 players
+        assert!(matches!(
+            config(3).validate(),
+            Err(Error::InvalidRoomConfig)
+        ));
+        assert!(config(4).validate().is_ok());
+    }
+
+    #[test]
+    fn test_valid_teams() {
+        let config = |teams: serde_json::Value| -> RoomConfig {
+            serde_json::from_value(json!({ "players": 4, "decks": 1, "teams": teams })).unwrap()
+        };
+        assert!(config(json!(null)).has_valid_teams());
+        assert!(config(json!([0, 1, 0, 1])).has_valid_teams());
+        assert!(config(json!([1, 1, 0, 0])).has_valid_teams());
+        // team 1 has no players
+        assert!(!config(json!([0, 2, 0, 2])).has_valid_teams());
+        // every seat needs a team, and only the seats of the room
+        assert!(!config(json!([0, 1, 0])).has_valid_teams());
+        assert!(!config(json!([0, 1, 0, 1, 0])).has_valid_teams());
+        assert!(matches!(
+            config(json!([0, 1, 0])).validate(),
+            Err(Error::InvalidTeams)
+        ));
+    }
+
+    #[test]
+    fn test_winner_reports_team_results() {
+        let mut room = full_room(json!({ "players": 2, "decks": 1, "teams": [0, 1] }));
+        assert_eq!(room.winner().team_results, None);
+        while let Some(player) = room.game.current_player() {
+            let action = room
+                .hint(player)
+                .unwrap()
+                .map_or(Action::Pass, Action::Play);
+            room.play(action, player, None).unwrap();
+        }
+        let scores = room.game.scores();
+        let results = room.winner().team_results.unwrap();
+        assert_eq!(results.scores, scores);
+        assert_eq!(results, room.export().unwrap().team_results.unwrap());
+    }
+
+    /// Make the `player` try to play a card they hold but cannot play.
+    fn play_illegal(room: &mut Room, player: usize) {
+        let hand = room.hand_of_player(player).unwrap();
+        let playable = room.game.playable_cards(player);
+        let card = hand.iter().find(|card| !playable.contains(card)).unwrap();
+        assert!(room.play(Action::Play(*card), player, None).is_err());
+    }
+
+    #[test]
+    fn test_illegal_moves_without_penalty() {
+        let mut room = full_room(json!({ "players": 2, "decks": 1, "illegal_move_limit": 2 }));
+        let player = room.game.current_player().unwrap();
+        play_illegal(&mut room, player);
+        play_illegal(&mut room, player);
+        assert_eq!(room.illegal_moves()[player], 2);
+        assert!(room.events.is_empty());
+        assert_eq!(room.game.current_player(), Some(player));
+    }
+
+    #[test]
+    fn test_illegal_moves_warn() {
+        let mut room = full_room(json!({
+            "players": 2,
+            "decks": 1,
+            "illegal_move_limit": 2,
+            "illegal_move_penalty": "warn"
+        }));
+        let player = room.game.current_player().unwrap();
+        play_illegal(&mut room, player);
+        assert!(room.events.is_empty());
+        play_illegal(&mut room, player);
+        assert!(matches!(
+            room.events[..],
+            [RoomEvent::IllegalMoveWarning { player: warned, attempts: 2 }] if warned == player
+        ));
+        assert_eq!(room.game.current_player(), Some(player));
+    }
+
+    #[test]
+    fn test_illegal_moves_skip_turn() {
+        let mut room = full_room(json!({
+            "players": 2,
+            "decks": 1,
+            "illegal_move_limit": 2,
+            "illegal_move_penalty": "skip_turn"
+        }));
+        let player = room.game.current_player().unwrap();
+        let other = 1 - player;
+        // the other player cannot be skipped out of turn, so they are warned
+        play_illegal(&mut room, other);
+        play_illegal(&mut room, other);
+        assert!(matches!(
+            room.events[..],
+            [RoomEvent::IllegalMoveWarning { player: warned, attempts: 2 }] if warned == other
+        ));
+        play_illegal(&mut room, player);
+        play_illegal(&mut room, player);
+        assert!(matches!(
+            room.events[1..],
+            [RoomEvent::IllegalMoveSkip { player: skipped, attempts: 2 }] if skipped == player
+        ));
+        assert_eq!(room.game.current_player(), Some(other));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_turn_timer_warns_then_skips() {
+        let mut room = full_room(json!({
+            "players": 2,
+            "decks": 1,
+            "turn_seconds": 30,
+            "turn_warning_seconds": 10
+        }));
+        let start = Instant::now();
+        let player = room.game.current_player().unwrap();
+        assert_eq!(
+            room.turn_timer_deadline(),
+            Some(start + Duration::from_secs(20))
+        );
+
+        // nothing happens before the warning is due
+        time::advance(Duration::from_secs(19)).await;
+        room.tick_turn_timer();
+        assert!(room.events.is_empty());
+
+        time::advance(Duration::from_secs(1)).await;
+        room.tick_turn_timer();
+        assert!(matches!(
+            room.events[..],
+            [RoomEvent::TurnWarning { player: warned, seconds_left: 10 }] if warned == player
+        ));
+        assert_eq!(
+            room.turn_timer_deadline(),
+            Some(start + Duration::from_secs(30))
+        );
+
+        time::advance(Duration::from_secs(10)).await;
+        room.tick_turn_timer();
+        assert!(matches!(
+            room.events[1..],
+            [RoomEvent::TurnTimedOut { player: skipped }] if skipped == player
+        ));
+        assert_eq!(room.game.current_player(), Some(1 - player));
+        // the next player gets a fresh turn
+        assert_eq!(
+            room.turn_timer_deadline(),
+            Some(Instant::now() + Duration::from_secs(20))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_run_loop_wakes_up_for_turn_timer() {
+        let room = full_room(json!({
+            "players": 2,
+            "decks": 1,
+            "turn_seconds": 30,
+            "turn_warning_seconds": 10
+        }));
+        let player = room.game.current_player().unwrap();
+        let (sender, receiver) = mpsc::channel(1);
+        tokio::spawn(room.run(receiver));
+
+        time::sleep(Duration::from_secs(31)).await;
+        let (responder, events) = oneshot::channel();
+        sender
+            .send(ServerRoomMessage::Events {
+                since: 0,
+                catch_up: false,
+                responder,
+            })
+            .await
+            .unwrap();
+        let (events, _) = events.await.unwrap();
+        assert!(matches!(
+            events[..],
+            [
+                RoomEvent::TurnWarning { player: warned, seconds_left: 10 },
+                RoomEvent::TurnTimedOut { player: skipped },
+            ] if warned == player && skipped == player
+        ));
+    }
+
+    #[test]
+    fn test_compact_game_state_size() {
+        let mut room = full_room(json!({ "players": 4, "decks": 1 }));
+        for _ in 0..20 {
+            let player = room.game.current_player().unwrap();
+            let action = room
+                .hint(player)
+                .unwrap()
+                .map_or(Action::Pass, Action::Play);
+            room.play(action, player, None).unwrap();
+        }
+        let state = room.game_state();
+        let full = serde_json::to_string(&state).unwrap().len();
+        let compact = serde_json::to_string(&CompactGameState::from(state))
+            .unwrap()
+            .len();
+        assert!(compact * 4 < full, "{compact} bytes against {full}");
+    }
+
+    #[test]
+    fn test_compact_game_state_keeps_large_card_counts() {
+        let room = full_room(json!({ "players": 2, "decks": 10 }));
+        let counts = room.card_counts();
+        assert!(counts.iter().all(|&count| count > u8::MAX as usize));
+        let compact = CompactGameState::from(room.game_state());
+        assert_eq!(compact.card_counts, Some(counts));
+    }
+
+    #[test]
+    fn test_kicked_player_cannot_block_stalemate() {
+        let mut room = full_room(json!({ "players": 3, "decks": 1 }));
+        let confirmation = room.confirmation(0).unwrap().nonce;
+        room.kick(0, 2, false, confirmation).unwrap();
+        // the others keep playing until only the kicked player's cards could
+        // continue the game
+        for _ in 0..200 {
+            let Some(player) = room.game.current_player() else {
+                break;
+            };
+            let action = room
+                .hint(player)
+                .unwrap()
+                .map_or(Action::Pass, Action::Play);
+            room.play(action, player, None).unwrap();
+        }
+        assert_eq!(room.game.end_reason(), Some(EndReason::Stalemate));
+        assert!(matches!(
+            room.events.last(),
+            Some(RoomEvent::GameOver {
+                reason: EndReason::Stalemate,
+                ..
+            })
+        ));
+    }
 }
@@ -1,28 +1,97 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use card_deck::standard_deck::Card;
+use badam_sat_protocol::{Action, GameState, MyTurn};
+use card_deck::standard_deck::{Card, Suit};
 use pasetors::{claims::Claims, keys::AsymmetricSecretKey, version4::V4};
+use sha2::{Digest, Sha256};
 
-use tokio::sync::{mpsc, oneshot};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{self, Instant, MissedTickBehavior},
+};
 use uuid::Uuid;
 
 use crate::{
     errors::Error,
-    rooms::{Action, GameState, Room},
+    rooms::{
+        Confirmation, GameExport, PlayOutcome, Room, RoomConfig, RoomEvent, RoomPassword,
+        TurnOrder, TurnTimer, ValidActions, Winner,
+    },
+    stats::StatsStore,
     RouterServerMessage,
 };
 
+/// How long to wait for a room to answer a message before giving up on it.
+const ROOM_SEND_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often to check that every room still answers messages.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(60);
+/// How long a room has to answer the watchdog before it is considered stuck.
+const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug)]
 pub(crate) struct Server {
     rooms: HashMap<Uuid, mpsc::Sender<ServerRoomMessage>>,
     max_rooms: usize,
+    max_game_duration: Option<Duration>,
+    instance_id: Uuid,
+    stats: Arc<Mutex<StatsStore>>,
+    /// Room that each player key last joined.
+    seats: HashMap<Uuid, Uuid>,
+    /// Quickplay rooms that still have free seats, with their settings and
+    /// seed commitment.
+    open_rooms: Vec<(RoomConfig, Uuid, Option<String>)>,
+}
+
+/// Seat taken by a player, through quickplay or by creating a room.
+pub(crate) struct Seat {
+    pub room_id: Uuid,
+    pub seed_commitment: Option<String>,
+    pub token: String,
+    pub player_id: usize,
 }
 
 pub(crate) enum ServerRoomMessage {
-    AddPlayer(oneshot::Sender<Result<Claims, Error>>),
+    AddPlayer {
+        player_key: Option<Uuid>,
+        password: Option<String>,
+        responder: oneshot::Sender<Result<Claims, Error>>,
+    },
     Play {
         action: Action,
         player: usize,
+        idempotency_key: Option<Uuid>,
+        responder: oneshot::Sender<Result<PlayOutcome, Error>>,
+    },
+    SetReady {
+        player: usize,
+        ready: bool,
+        responder: oneshot::Sender<Result<(), Error>>,
+    },
+    Start {
+        player: usize,
+        responder: oneshot::Sender<Result<(), Error>>,
+    },
+    Confirm {
+        player: usize,
+        responder: oneshot::Sender<Result<Confirmation, Error>>,
+    },
+    Kick {
+        player: usize,
+        kicked: usize,
+        discard_cards: bool,
+        confirmation: Uuid,
+        responder: oneshot::Sender<Result<(), Error>>,
+    },
+    Reconfigure {
+        player: usize,
+        players: Option<usize>,
+        decks: Option<usize>,
+        teams: Option<Vec<usize>>,
+        confirmation: Uuid,
         responder: oneshot::Sender<Result<(), Error>>,
     },
     GameOver(oneshot::Sender<bool>),
@@ -31,181 +100,1306 @@ pub(crate) enum ServerRoomMessage {
         player: usize,
         responder: oneshot::Sender<Result<Vec<Card>, Error>>,
     },
+    MoveCount {
+        player: usize,
+        responder: oneshot::Sender<Result<usize, Error>>,
+    },
+    MyTurn {
+        player: usize,
+        responder: oneshot::Sender<Result<MyTurn, Error>>,
+    },
+    Hint {
+        player: usize,
+        responder: oneshot::Sender<Result<Option<Card>, Error>>,
+    },
+    DealtCards {
+        player: usize,
+        responder: oneshot::Sender<Result<Vec<Card>, Error>>,
+    },
+    DealSequence(oneshot::Sender<Vec<usize>>),
+    Placement {
+        player: usize,
+        card: Card,
+        responder: oneshot::Sender<Result<Option<usize>, Error>>,
+    },
+    Ping(oneshot::Sender<()>),
+    /// Check from the watchdog, which unlike [`ServerRoomMessage::Ping`] does
+    /// not keep the room from idling out.
+    Heartbeat(oneshot::Sender<()>),
+    Activity(oneshot::Sender<Option<Instant>>),
+    ValidActions(oneshot::Sender<ValidActions>),
+    MissingCards(oneshot::Sender<HashMap<Suit, Vec<Card>>>),
+    CardCounts(oneshot::Sender<Option<Vec<usize>>>),
+    IllegalMoves(oneshot::Sender<Vec<usize>>),
+    TurnOrder(oneshot::Sender<TurnOrder>),
+    TurnTimer(oneshot::Sender<Option<TurnTimer>>),
+    Winner(oneshot::Sender<Winner>),
     GameState(oneshot::Sender<GameState>),
+    #[cfg(feature = "debug")]
+    Debug(oneshot::Sender<String>),
+    Seed(oneshot::Sender<Result<[u8; 32], Error>>),
+    Export(oneshot::Sender<Result<GameExport, Error>>),
+    Events {
+        since: usize,
+        catch_up: bool,
+        responder: oneshot::Sender<(Vec<RoomEvent>, usize)>,
+    },
 }
 
 impl Server {
     /// Create a server that can support `max_rooms` concurrent games and uses
     /// the ED25519 `key_pair` keys for player token signing.
-    pub fn spawn(max_rooms: usize, receiver: mpsc::Receiver<RouterServerMessage>) {
+    ///
+    /// The `instance_id` is added to the claims of every token the server
+    /// issues. Game results of players with a player key go to the `stats`.
+    pub fn spawn(
+        max_rooms: usize,
+        max_game_duration: Option<Duration>,
+        instance_id: Uuid,
+        stats: StatsStore,
+        receiver: mpsc::Receiver<RouterServerMessage>,
+    ) {
         let server = Server {
             rooms: HashMap::new(),
             max_rooms,
+            max_game_duration,
+            instance_id,
+            stats: Arc::new(Mutex::new(stats)),
+            seats: HashMap::new(),
+            open_rooms: Vec::new(),
         };
         tokio::spawn(server.run(receiver));
     }
 
+    /// Create a server for tests, without spawning it.
+    #[cfg(test)]
+    fn test_server(max_rooms: usize) -> Server {
+        Server {
+            rooms: HashMap::new(),
+            max_rooms,
+            max_game_duration: None,
+            instance_id: Uuid::new_v4(),
+            stats: Default::default(),
+            seats: HashMap::new(),
+            open_rooms: Vec::new(),
+        }
+    }
+
     pub async fn run(mut self, mut receiver: mpsc::Receiver<RouterServerMessage>) {
         fn respond<T>(responder: oneshot::Sender<T>, msg: T) -> bool {
             responder.send(msg).is_ok()
         }
 
-        while let Some(msg) = receiver.recv().await {
+        let mut watchdog = time::interval(WATCHDOG_INTERVAL);
+        watchdog.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let (stuck_sender, mut stuck_receiver) = mpsc::channel(1);
+        loop {
+            let msg = tokio::select! {
+                msg = receiver.recv() => match msg {
+                    Some(msg) => msg,
+                    None => break,
+                },
+                _ = watchdog.tick() => {
+                    // waiting for the heartbeats here would hold up every
+                    // other request, so it happens in its own task
+                    let rooms = self.room_senders();
+                    let stuck_sender = stuck_sender.clone();
+                    tokio::spawn(async move {
+                        let _ = stuck_sender.send(find_stuck_rooms(rooms).await).await;
+                    });
+                    continue;
+                }
+                Some(stuck) = stuck_receiver.recv() => {
+                    self.remove_stuck_rooms(stuck);
+                    metrics::gauge!("rooms_active", self.rooms.len() as f64);
+                    continue;
+                }
+            };
             let success = match msg {
                 RouterServerMessage::CreateRoom {
-                    players,
-                    decks,
+                    config,
+                    password,
                     responder,
-                } => respond(responder, self.create_room(players, decks)),
+                } => respond(responder, self.create_room(config, password.as_deref())),
                 RouterServerMessage::JoinRoom {
                     room,
+                    player_key,
+                    password,
+                    secret_key,
+                    responder,
+                } => respond(
+                    responder,
+                    self.join(&room, player_key, password, &secret_key).await,
+                ),
+                RouterServerMessage::CreateAndJoin {
+                    config,
+                    password,
+                    player_key,
                     secret_key,
                     responder,
-                } => respond(responder, self.join(&room, &secret_key).await),
+                } => respond(
+                    responder,
+                    self.create_and_join(config, password, player_key, &secret_key)
+                        .await,
+                ),
+                RouterServerMessage::Quickplay {
+                    config,
+                    player_key,
+                    secret_key,
+                    responder,
+                } => respond(
+                    responder,
+                    self.quickplay(config, player_key, &secret_key).await,
+                ),
+                RouterServerMessage::Stats {
+                    player_key,
+                    responder,
+                } => respond(responder, self.stats.lock().unwrap().get(&player_key)),
                 RouterServerMessage::Play {
                     action,
                     player,
                     room,
+                    idempotency_key,
+                    responder,
+                } => respond(
+                    responder,
+                    self.play(action, player, &room, idempotency_key).await,
+                ),
+                RouterServerMessage::SetReady {
+                    player,
+                    room,
+                    ready,
+                    responder,
+                } => respond(responder, self.set_ready(&room, player, ready).await),
+                RouterServerMessage::Start {
+                    player,
+                    room,
+                    responder,
+                } => respond(responder, self.start(&room, player).await),
+                RouterServerMessage::Confirm {
+                    player,
+                    room,
+                    responder,
+                } => respond(responder, self.confirmation(&room, player).await),
+                RouterServerMessage::Kick {
+                    player,
+                    room,
+                    kicked,
+                    discard_cards,
+                    confirmation,
+                    responder,
+                } => respond(
+                    responder,
+                    self.kick(&room, player, kicked, discard_cards, confirmation)
+                        .await,
+                ),
+                RouterServerMessage::Reconfigure {
+                    player,
+                    room,
+                    players,
+                    decks,
+                    teams,
+                    confirmation,
+                    responder,
+                } => respond(
                     responder,
-                } => respond(responder, self.play(action, player, &room).await),
+                    self.reconfigure(&room, player, players, decks, teams, confirmation)
+                        .await,
+                ),
                 RouterServerMessage::GetHand {
                     player,
                     room,
                     responder,
                 } => respond(responder, self.hand(&room, player).await),
+                RouterServerMessage::MoveCount {
+                    player,
+                    room,
+                    responder,
+                } => respond(responder, self.move_count(&room, player).await),
+                RouterServerMessage::MyTurn {
+                    player,
+                    room,
+                    responder,
+                } => respond(responder, self.my_turn(&room, player).await),
+                RouterServerMessage::Hint {
+                    player,
+                    room,
+                    responder,
+                } => respond(responder, self.hint(&room, player).await),
+                RouterServerMessage::DealtCards {
+                    player,
+                    room,
+                    responder,
+                } => respond(responder, self.dealt_cards(&room, player).await),
+                RouterServerMessage::DealSequence { room, responder } => {
+                    respond(responder, self.deal_sequence(&room).await)
+                }
+                RouterServerMessage::Placement {
+                    player,
+                    room,
+                    card,
+                    responder,
+                } => respond(responder, self.placement(&room, player, card).await),
                 RouterServerMessage::LastMove { room, responder } => {
                     respond(responder, self.last_move(&room).await)
                 }
+                RouterServerMessage::Ping { room, responder } => {
+                    respond(responder, self.ping(&room).await)
+                }
+                RouterServerMessage::RoomExists { room, responder } => {
+                    respond(responder, self.room_exists(&room))
+                }
+                RouterServerMessage::ValidActions { room, responder } => {
+                    respond(responder, self.valid_actions(&room).await)
+                }
+                RouterServerMessage::MissingCards { room, responder } => {
+                    respond(responder, self.missing_cards(&room).await)
+                }
+                RouterServerMessage::CardCounts { room, responder } => {
+                    respond(responder, self.card_counts(&room).await)
+                }
+                RouterServerMessage::IllegalMoves { room, responder } => {
+                    respond(responder, self.illegal_moves(&room).await)
+                }
+                RouterServerMessage::TurnOrder { room, responder } => {
+                    respond(responder, self.turn_order(&room).await)
+                }
+                RouterServerMessage::TurnTimer { room, responder } => {
+                    respond(responder, self.turn_timer(&room).await)
+                }
+                RouterServerMessage::Winner { room, responder } => {
+                    respond(responder, self.winner(&room).await)
+                }
+                RouterServerMessage::PickActiveRoom(responder) => {
+                    // asking every room would hold up every other request, so
+                    // it happens in its own task
+                    let rooms = self.room_senders();
+                    tokio::spawn(async move {
+                        respond(responder, pick_active_room(rooms).await);
+                    });
+                    true
+                }
                 RouterServerMessage::GameState { room, responder } => {
                     respond(responder, self.game_state(&room).await)
                 }
+                #[cfg(feature = "debug")]
+                RouterServerMessage::Debug { room, responder } => {
+                    respond(responder, self.debug(&room).await)
+                }
+                RouterServerMessage::Seed { room, responder } => {
+                    respond(responder, self.seed(&room).await)
+                }
+                RouterServerMessage::Export { room, responder } => {
+                    respond(responder, self.export(&room).await)
+                }
+                RouterServerMessage::Events {
+                    room,
+                    since,
+                    catch_up,
+                    responder,
+                } => respond(responder, self.events(&room, since, catch_up).await),
             };
             if !success {
                 log::warn!("failed to send to api, exiting");
                 break;
             }
             self.rooms.retain(|_, sender| !sender.is_closed());
+            self.seats
+                .retain(|_, room_id| self.rooms.contains_key(room_id));
+            metrics::gauge!("rooms_active", self.rooms.len() as f64);
         }
     }
 
     /// Create a room in the server.
     ///
-    /// Currently [`ClientError::ServerFull`] is the only error this method can
-    /// return.
-    pub fn create_room(&mut self, players: usize, decks: usize) -> Result<Uuid, Error> {
+    /// The cards are shuffled with a random seed, so that completed games can
+    /// be exported and replayed. If the room is seeded, a hex encoded SHA-256
+    /// commitment of the seed is returned along with the room id. The seed
+    /// itself is revealed only after the game is over.
+    ///
+    /// Currently [`ClientError::ServerFull`], [`ClientError::InvalidTheme`],
+    /// [`ClientError::InvalidRoomConfig`] and [`ClientError::InvalidTeams`] are
+    /// the only errors this method can return.
+    pub fn create_room(
+        &mut self,
+        config: RoomConfig,
+        password: Option<&str>,
+    ) -> Result<(Uuid, Option<String>), Error> {
         if self.max_rooms == self.rooms.len() {
             return Err(Error::ServerFull);
         }
+        config.validate()?;
+        let seed: [u8; 32] = rand::random();
+        let commitment = config.seeded.then(|| to_hex(&Sha256::digest(seed)));
         let (sender, receiver) = mpsc::channel(10);
-        Room::spawn(players, decks, receiver);
+        Room::spawn(
+            config,
+            seed,
+            password.map(RoomPassword::new),
+            self.max_game_duration,
+            self.stats.clone(),
+            receiver,
+        );
         let room_id = Uuid::new_v4();
         self.rooms.insert(room_id, sender);
-        Ok(room_id)
+        metrics::increment_counter!("rooms_created_total");
+        Ok((room_id, commitment))
+    }
+
+    /// Send the message built by `msg` around a responder to the room
+    /// `room_id` and wait for its answer.
+    ///
+    /// A room that does not take the message and answer it within
+    /// [`ROOM_SEND_TIMEOUT`] is considered stuck and removed from the server.
+    async fn request<T>(
+        &mut self,
+        room_id: &Uuid,
+        msg: impl FnOnce(oneshot::Sender<T>) -> ServerRoomMessage,
+    ) -> Result<T, Error> {
+        let room_sender = self.rooms.get(room_id).ok_or(Error::InvalidRoomId)?.clone();
+        let (responder, receiver) = oneshot::channel();
+        let answer = time::timeout(ROOM_SEND_TIMEOUT, async move {
+            room_sender
+                .send(msg(responder))
+                .await
+                .map_err(|_| Error::InvalidRoomId)?;
+            receiver.await.map_err(|_| Error::InvalidRoomId)
+        })
+        .await;
+        answer.unwrap_or_else(|_| {
+            log::warn!("room {room_id} is not responding, removing it");
+            self.rooms.remove(room_id);
+            Err(Error::UnexpectedTermination)
+        })
+    }
+
+    /// Get a sender to every room, for asking them all from another task.
+    fn room_senders(&self) -> Vec<(Uuid, mpsc::Sender<ServerRoomMessage>)> {
+        self.rooms
+            .iter()
+            .map(|(room_id, sender)| (*room_id, sender.clone()))
+            .collect()
     }
 
-    /// Join the room `room_id` in this server as a player.
+    /// Remove the `stuck` rooms found by the watchdog, along with the rooms
+    /// that have closed since.
+    fn remove_stuck_rooms(&mut self, stuck: Vec<Uuid>) {
+        for room_id in stuck {
+            log::warn!("room {room_id} did not answer the watchdog, removing it");
+            self.rooms.remove(&room_id);
+        }
+        self.rooms.retain(|_, sender| !sender.is_closed());
+        self.open_rooms
+            .retain(|(_, room_id, _)| self.rooms.contains_key(room_id));
+    }
+
+    /// Join the room `room_id` in this server as a player, returning the
+    /// player's token and their seat in the room.
+    ///
+    /// A `player_key` can only have one seat at a time, in a room whose game
+    /// is not over yet.
     ///
-    /// Currently [`ClientError::RoomFull`] and [`ClientError::InvalidRoomId`]
-    /// are the only errors this method can return.
+    /// Currently [`ClientError::RoomFull`], [`ClientError::InvalidRoomId`],
+    /// [`ClientError::AlreadyInGame`], [`ClientError::WrongPassword`] and
+    /// [`ClientError::UnexpectedTermination`] are the only errors this method
+    /// can return.
     pub async fn join(
-        &self,
+        &mut self,
         room_id: &Uuid,
+        player_key: Option<Uuid>,
+        password: Option<String>,
+        secret_key: &AsymmetricSecretKey<V4>,
+    ) -> Result<(String, usize), Error> {
+        if let Some(seat) = player_key.and_then(|key| self.seats.get(&key).copied()) {
+            // rooms that are gone count as over
+            if !self.is_game_over(&seat).await.unwrap_or(true) {
+                return Err(Error::AlreadyInGame);
+            }
+        }
+        let mut claim = self
+            .request(room_id, |responder| ServerRoomMessage::AddPlayer {
+                player_key,
+                password,
+                responder,
+            })
+            .await??;
+        claim
+            .add_additional("room_id", serde_json::to_value(room_id).unwrap())
+            .unwrap();
+        claim
+            .add_additional(
+                "instance_id",
+                serde_json::to_value(self.instance_id).unwrap(),
+            )
+            .unwrap();
+        let player_id = claim
+            .get_claim("sub")
+            .and_then(|sub| sub.as_str()?.parse().ok())
+            .unwrap();
+        let token = pasetors::public::sign(secret_key, &claim, None, None).unwrap();
+        if let Some(player_key) = player_key {
+            self.seats.insert(player_key, *room_id);
+        }
+        Ok((token, player_id))
+    }
+
+    /// Create a room and seat its creator in it right away, before anyone
+    /// else can join, as the player identified by the `player_key` if one is
+    /// given.
+    ///
+    /// The room is removed again if the creator cannot join it.
+    pub async fn create_and_join(
+        &mut self,
+        config: RoomConfig,
+        password: Option<String>,
+        player_key: Option<Uuid>,
         secret_key: &AsymmetricSecretKey<V4>,
-    ) -> Result<String, Error> {
-        match self.rooms.get(room_id) {
-            Some(room_sender) => {
-                let (sender, receiver): (oneshot::Sender<Result<Claims, Error>>, _) =
-                    oneshot::channel();
-                room_sender
-                    .send(ServerRoomMessage::AddPlayer(sender))
-                    .await
-                    .map_err(|_| Error::InvalidRoomId)?;
-                let mut claim = receiver.await.map_err(|_| Error::InvalidRoomId)??;
-                claim
-                    .add_additional("room_id", serde_json::to_value(room_id).unwrap())
-                    .unwrap();
-                let token = pasetors::public::sign(secret_key, &claim, None, None).unwrap();
-                Ok(token)
+    ) -> Result<Seat, Error> {
+        let (room_id, seed_commitment) = self.create_room(config, password.as_deref())?;
+        match self.join(&room_id, player_key, password, secret_key).await {
+            Ok((token, player_id)) => Ok(Seat {
+                room_id,
+                seed_commitment,
+                token,
+                player_id,
+            }),
+            Err(err) => {
+                self.rooms.remove(&room_id);
+                Err(err)
             }
-            None => Err(Error::InvalidRoomId),
         }
     }
 
+    /// Join any quickplay room with the `config` that has a free seat, or a
+    /// new one if there is none.
+    ///
+    /// Rooms can fill up through regular joins after being listed, in which
+    /// case the next matching room is tried. A room created for the player is
+    /// removed again if they cannot join it.
+    pub async fn quickplay(
+        &mut self,
+        config: RoomConfig,
+        player_key: Option<Uuid>,
+        secret_key: &AsymmetricSecretKey<V4>,
+    ) -> Result<Seat, Error> {
+        config.validate()?;
+        loop {
+            let (index, created) = match self
+                .open_rooms
+                .iter()
+                .position(|(open, _, _)| open == &config)
+            {
+                Some(index) => (index, false),
+                None => {
+                    let (room_id, commitment) = self.create_room(config.clone(), None)?;
+                    self.open_rooms.push((config.clone(), room_id, commitment));
+                    (self.open_rooms.len() - 1, true)
+                }
+            };
+            let (_, room_id, seed_commitment) = self.open_rooms[index].clone();
+            match self.join(&room_id, player_key, None, secret_key).await {
+                Ok((token, player_id)) => {
+                    if player_id + 1 == config.players {
+                        self.open_rooms.remove(index);
+                    }
+                    return Ok(Seat {
+                        room_id,
+                        seed_commitment,
+                        token,
+                        player_id,
+                    });
+                }
+                Err(Error::RoomFull | Error::InvalidRoomId | Error::UnexpectedTermination) => {
+                    self.open_rooms.remove(index);
+                }
+                Err(err) => {
+                    if created {
+                        self.open_rooms.remove(index);
+                        self.rooms.remove(&room_id);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    async fn is_game_over(&mut self, room_id: &Uuid) -> Result<bool, Error> {
+        self.request(room_id, ServerRoomMessage::GameOver).await
+    }
+
     /// Make the `action` playe for the `player` in the room `room_id`.
     pub async fn play(
         &mut self,
         action: Action,
         player: usize,
         room_id: &Uuid,
+        idempotency_key: Option<Uuid>,
+    ) -> Result<PlayOutcome, Error> {
+        let outcome = self
+            .request(room_id, |responder| ServerRoomMessage::Play {
+                action,
+                player,
+                idempotency_key,
+                responder,
+            })
+            .await??;
+        self.request(room_id, ServerRoomMessage::GameOver).await?;
+        Ok(outcome)
+    }
+
+    pub async fn set_ready(
+        &mut self,
+        room_id: &Uuid,
+        player: usize,
+        ready: bool,
+    ) -> Result<(), Error> {
+        self.request(room_id, |responder| ServerRoomMessage::SetReady {
+            player,
+            ready,
+            responder,
+        })
+        .await?
+    }
+
+    pub async fn start(&mut self, room_id: &Uuid, player: usize) -> Result<(), Error> {
+        self.request(room_id, |responder| ServerRoomMessage::Start {
+            player,
+            responder,
+        })
+        .await?
+    }
+
+    pub async fn confirmation(
+        &mut self,
+        room_id: &Uuid,
+        player: usize,
+    ) -> Result<Confirmation, Error> {
+        self.request(room_id, |responder| ServerRoomMessage::Confirm {
+            player,
+            responder,
+        })
+        .await?
+    }
+
+    pub async fn kick(
+        &mut self,
+        room_id: &Uuid,
+        player: usize,
+        kicked: usize,
+        discard_cards: bool,
+        confirmation: Uuid,
     ) -> Result<(), Error> {
-        match self.rooms.get(room_id) {
-            Some(room_sender) => {
-                let (sender, receiver) = oneshot::channel();
-                room_sender
-                    .send(ServerRoomMessage::Play {
-                        action,
-                        player,
-                        responder: sender,
-                    })
-                    .await
-                    .map_err(|_| Error::InvalidRoomId)?;
-                let resp: Result<(), Error> = receiver.await.map_err(|_| Error::InvalidRoomId)?;
-                resp?;
-                let (sender, receiver) = oneshot::channel();
-                room_sender
-                    .send(ServerRoomMessage::GameOver(sender))
-                    .await?;
-                receiver.await?;
-                Ok(())
-            }
-            None => Err(Error::InvalidRoomId),
-        }
-    }
-
-    pub async fn hand(&self, room_id: &Uuid, player: usize) -> Result<Vec<Card>, Error> {
-        match self.rooms.get(room_id) {
-            Some(room_sender) => {
-                let (sender, receiver) = oneshot::channel();
-                room_sender
-                    .send(ServerRoomMessage::Hand {
-                        player,
-                        responder: sender,
-                    })
-                    .await
-                    .map_err(|_| Error::InvalidRoomId)?;
-                receiver.await.map_err(|_| Error::InvalidRoomId)?
-            }
-            None => Err(Error::InvalidRoomId),
+        self.request(room_id, |responder| ServerRoomMessage::Kick {
+            player,
+            kicked,
+            discard_cards,
+            confirmation,
+            responder,
+        })
+        .await?
+    }
+
+    /// Change the settings of the room `room_id` as its host.
+    ///
+    /// A quickplay room no longer matches the settings it was listed with
+    /// afterwards, so it is not offered to quickplay anymore.
+    pub async fn reconfigure(
+        &mut self,
+        room_id: &Uuid,
+        player: usize,
+        players: Option<usize>,
+        decks: Option<usize>,
+        teams: Option<Vec<usize>>,
+        confirmation: Uuid,
+    ) -> Result<(), Error> {
+        self.request(room_id, |responder| ServerRoomMessage::Reconfigure {
+            player,
+            players,
+            decks,
+            teams,
+            confirmation,
+            responder,
+        })
+        .await??;
+        self.open_rooms.retain(|(_, open, _)| open != room_id);
+        Ok(())
+    }
+
+    pub async fn hand(&mut self, room_id: &Uuid, player: usize) -> Result<Vec<Card>, Error> {
+        self.request(room_id, |responder| ServerRoomMessage::Hand {
+            player,
+            responder,
+        })
+        .await?
+    }
+
+    pub async fn move_count(&mut self, room_id: &Uuid, player: usize) -> Result<usize, Error> {
+        self.request(room_id, |responder| ServerRoomMessage::MoveCount {
+            player,
+            responder,
+        })
+        .await?
+    }
+
+    pub async fn my_turn(&mut self, room_id: &Uuid, player: usize) -> Result<MyTurn, Error> {
+        self.request(room_id, |responder| ServerRoomMessage::MyTurn {
+            player,
+            responder,
+        })
+        .await?
+    }
+
+    pub async fn hint(&mut self, room_id: &Uuid, player: usize) -> Result<Option<Card>, Error> {
+        self.request(room_id, |responder| ServerRoomMessage::Hint {
+            player,
+            responder,
+        })
+        .await?
+    }
+
+    pub async fn dealt_cards(&mut self, room_id: &Uuid, player: usize) -> Result<Vec<Card>, Error> {
+        self.request(room_id, |responder| ServerRoomMessage::DealtCards {
+            player,
+            responder,
+        })
+        .await?
+    }
+
+    pub async fn deal_sequence(&mut self, room_id: &Uuid) -> Result<Vec<usize>, Error> {
+        self.request(room_id, ServerRoomMessage::DealSequence).await
+    }
+
+    pub async fn placement(
+        &mut self,
+        room_id: &Uuid,
+        player: usize,
+        card: Card,
+    ) -> Result<Option<usize>, Error> {
+        self.request(room_id, |responder| ServerRoomMessage::Placement {
+            player,
+            card,
+            responder,
+        })
+        .await?
+    }
+
+    pub async fn last_move(&mut self, room_id: &Uuid) -> Result<Action, Error> {
+        self.request(room_id, ServerRoomMessage::LastMove)
+            .await?
+            .ok_or(Error::NoMove)
+    }
+
+    /// Check whether the room `room_id` is still open.
+    pub fn room_exists(&self, room_id: &Uuid) -> bool {
+        self.rooms
+            .get(room_id)
+            .is_some_and(|sender| !sender.is_closed())
+    }
+
+    /// Keep the room `room_id` from being closed for inactivity.
+    pub async fn ping(&mut self, room_id: &Uuid) -> Result<(), Error> {
+        self.request(room_id, ServerRoomMessage::Ping).await
+    }
+
+    pub async fn valid_actions(&mut self, room_id: &Uuid) -> Result<ValidActions, Error> {
+        self.request(room_id, ServerRoomMessage::ValidActions).await
+    }
+
+    pub async fn missing_cards(
+        &mut self,
+        room_id: &Uuid,
+    ) -> Result<HashMap<Suit, Vec<Card>>, Error> {
+        self.request(room_id, ServerRoomMessage::MissingCards).await
+    }
+
+    pub async fn card_counts(&mut self, room_id: &Uuid) -> Result<Vec<usize>, Error> {
+        self.request(room_id, ServerRoomMessage::CardCounts)
+            .await?
+            .ok_or(Error::CountsHidden)
+    }
+
+    pub async fn illegal_moves(&mut self, room_id: &Uuid) -> Result<Vec<usize>, Error> {
+        self.request(room_id, ServerRoomMessage::IllegalMoves).await
+    }
+
+    pub async fn turn_order(&mut self, room_id: &Uuid) -> Result<TurnOrder, Error> {
+        self.request(room_id, ServerRoomMessage::TurnOrder).await
+    }
+
+    pub async fn turn_timer(&mut self, room_id: &Uuid) -> Result<Option<TurnTimer>, Error> {
+        self.request(room_id, ServerRoomMessage::TurnTimer).await
+    }
+
+    pub async fn winner(&mut self, room_id: &Uuid) -> Result<Winner, Error> {
+        self.request(room_id, ServerRoomMessage::Winner).await
+    }
+
+    pub async fn game_state(&mut self, room_id: &Uuid) -> Result<GameState, Error> {
+        self.request(room_id, ServerRoomMessage::GameState).await
+    }
+
+    /// Dump the full internal state of the room, including every player's
+    /// hand.
+    #[cfg(feature = "debug")]
+    pub async fn debug(&mut self, room_id: &Uuid) -> Result<String, Error> {
+        self.request(room_id, ServerRoomMessage::Debug).await
+    }
+
+    pub async fn seed(&mut self, room_id: &Uuid) -> Result<[u8; 32], Error> {
+        self.request(room_id, ServerRoomMessage::Seed).await?
+    }
+
+    pub async fn export(&mut self, room_id: &Uuid) -> Result<GameExport, Error> {
+        self.request(room_id, ServerRoomMessage::Export).await?
+    }
+
+    pub async fn events(
+        &mut self,
+        room_id: &Uuid,
+        since: usize,
+        catch_up: bool,
+    ) -> Result<(Vec<RoomEvent>, usize), Error> {
+        self.request(room_id, |responder| ServerRoomMessage::Events {
+            since,
+            catch_up,
+            responder,
+        })
+        .await
+    }
+}
+
+/// Find the `rooms` that do not answer a heartbeat within
+/// [`WATCHDOG_TIMEOUT`], as they are stuck and would otherwise only be noticed
+/// once a client tries to use them.
+async fn find_stuck_rooms(rooms: Vec<(Uuid, mpsc::Sender<ServerRoomMessage>)>) -> Vec<Uuid> {
+    let deadline = Instant::now() + WATCHDOG_TIMEOUT;
+    let mut heartbeats = Vec::new();
+    let mut stuck = Vec::new();
+    for (room_id, sender) in rooms {
+        let (responder, receiver) = oneshot::channel();
+        match sender.try_send(ServerRoomMessage::Heartbeat(responder)) {
+            Ok(()) => heartbeats.push((room_id, receiver)),
+            // the server waits for every answer before sending more, so a
+            // full queue means the room stopped reading it
+            Err(mpsc::error::TrySendError::Full(_)) => stuck.push(room_id),
+            Err(mpsc::error::TrySendError::Closed(_)) => {}
+        }
+    }
+    for (room_id, heartbeat) in heartbeats {
+        if time::timeout_at(deadline, heartbeat).await.is_err() {
+            stuck.push(room_id);
         }
     }
+    stuck
+}
 
-    pub async fn last_move(&self, room_id: &Uuid) -> Result<Action, Error> {
-        match self.rooms.get(room_id) {
-            Some(room_sender) => {
-                let (sender, receiver) = oneshot::channel();
-                room_sender
-                    .send(ServerRoomMessage::LastMove(sender))
-                    .await
-                    .map_err(|_| Error::InvalidRoomId)?;
-                let maybe_move = receiver.await.map_err(|_| Error::InvalidRoomId)?;
-                maybe_move.ok_or(Error::NoMove)
+/// Pick the room with the most recent client activity among the `rooms`
+/// whose game is in progress, for spectating.
+///
+/// Every room is asked at once, and rooms that do not answer within
+/// [`ROOM_SEND_TIMEOUT`] are left out.
+///
+/// Currently [`ClientError::NoActiveRoom`] is the only error this function
+/// can return.
+async fn pick_active_room(
+    rooms: Vec<(Uuid, mpsc::Sender<ServerRoomMessage>)>,
+) -> Result<Uuid, Error> {
+    let deadline = Instant::now() + ROOM_SEND_TIMEOUT;
+    let mut answers = Vec::new();
+    for (room_id, sender) in rooms {
+        let (responder, receiver) = oneshot::channel();
+        if sender
+            .try_send(ServerRoomMessage::Activity(responder))
+            .is_ok()
+        {
+            answers.push((room_id, receiver));
+        }
+    }
+    let mut latest: Option<(Uuid, Instant)> = None;
+    for (room_id, answer) in answers {
+        if let Ok(Ok(Some(last_activity))) = time::timeout_at(deadline, answer).await {
+            if latest.is_none_or(|(_, latest)| last_activity > latest) {
+                latest = Some((room_id, last_activity));
             }
-            None => Err(Error::InvalidRoomId),
         }
     }
+    latest
+        .map(|(room_id, _)| room_id)
+        .ok_or(Error::NoActiveRoom)
+}
+
+/// Encode `bytes` as a lowercase hexadecimal string.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use badam_sat_protocol::Action;
+    use pasetors::{
+        keys::{AsymmetricKeyPair, Generate},
+        version4::V4,
+    };
+    use tokio::{sync::mpsc, time::Instant};
+    use uuid::Uuid;
+
+    use serde_json::json;
 
-    pub async fn game_state(&self, room_id: &Uuid) -> Result<GameState, Error> {
-        match self.rooms.get(room_id) {
-            Some(room_sender) => {
-                let (sender, receiver) = oneshot::channel();
-                room_sender
-                    .send(ServerRoomMessage::GameState(sender))
-                    .await
-                    .map_err(|_| Error::InvalidRoomId)?;
-                let maybe_state = receiver.await.map_err(|_| Error::InvalidRoomId)?;
-                Ok(maybe_state)
+    use super::{find_stuck_rooms, pick_active_room, Server, ServerRoomMessage};
+    use crate::{
+        errors::Error,
+        rooms::{Confirmation, RoomConfig, RoomEvent},
+    };
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stuck_room_is_removed() {
+        let mut server = Server::test_server(1);
+        // a room that takes its messages but never answers them
+        let (sender, mut receiver) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let mut unanswered = Vec::new();
+            while let Some(msg) = receiver.recv().await {
+                unanswered.push(msg);
             }
-            None => Err(Error::InvalidRoomId),
+        });
+        let room_id = Uuid::new_v4();
+        server.rooms.insert(room_id, sender);
+
+        assert!(matches!(
+            server.game_state(&room_id).await,
+            Err(Error::UnexpectedTermination)
+        ));
+        assert!(server.rooms.is_empty());
+        assert!(matches!(
+            server.game_state(&room_id).await,
+            Err(Error::InvalidRoomId)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_room() {
+        let mut server = Server::test_server(1);
+        let config = serde_json::from_value(json!({ "players": 3, "decks": 1 })).unwrap();
+        let (room_id, _) = server.create_room(config, None).unwrap();
+
+        let nonce = |confirmation: Confirmation| {
+            serde_json::to_value(confirmation).unwrap()["nonce"]
+                .as_str()
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+        assert!(matches!(
+            server.confirmation(&room_id, 1).await,
+            Err(Error::NotHost)
+        ));
+        let confirmation = nonce(server.confirmation(&room_id, 0).await.unwrap());
+        assert!(matches!(
+            server
+                .reconfigure(&room_id, 1, Some(2), None, None, confirmation)
+                .await,
+            Err(Error::NotHost)
+        ));
+        assert!(matches!(
+            server
+                .reconfigure(&room_id, 0, None, Some(0), None, confirmation)
+                .await,
+            Err(Error::InvalidRoomConfig)
+        ));
+        // the failed attempt used up the confirmation
+        assert!(matches!(
+            server
+                .reconfigure(&room_id, 0, Some(4), Some(2), None, confirmation)
+                .await,
+            Err(Error::InvalidConfirmation)
+        ));
+        let confirmation = nonce(server.confirmation(&room_id, 0).await.unwrap());
+        server
+            .reconfigure(&room_id, 0, Some(4), Some(2), None, confirmation)
+            .await
+            .unwrap();
+        let (events, _) = server.events(&room_id, 0, false).await.unwrap();
+        assert!(matches!(
+            events[..],
+            [RoomEvent::RoomReconfigured {
+                players: 4,
+                decks: 2
+            }]
+        ));
+    }
+
+    /// Reconfigure the room `room_id` as its host, confirming it first.
+    async fn reconfigure(
+        server: &mut Server,
+        room_id: &Uuid,
+        players: Option<usize>,
+        decks: Option<usize>,
+        teams: Option<Vec<usize>>,
+    ) -> Result<(), Error> {
+        let confirmation = server.confirmation(room_id, 0).await?;
+        let nonce = serde_json::to_value(confirmation).unwrap()["nonce"]
+            .as_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        server
+            .reconfigure(room_id, 0, players, decks, teams, nonce)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_shrink_room_before_start() {
+        let mut server = Server::test_server(1);
+        let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+        let config = serde_json::from_value(json!({ "players": 4, "decks": 1 })).unwrap();
+        let (room_id, _) = server.create_room(config, None).unwrap();
+        for _ in 0..3 {
+            server
+                .join(&room_id, None, None, &key_pair.secret)
+                .await
+                .unwrap();
+        }
+        assert!(matches!(
+            reconfigure(&mut server, &room_id, Some(4), Some(2), None).await,
+            Err(Error::RoomLocked)
+        ));
+        assert!(matches!(
+            reconfigure(&mut server, &room_id, Some(2), None, None).await,
+            Err(Error::InvalidRoomConfig)
+        ));
+        reconfigure(&mut server, &room_id, Some(3), None, None)
+            .await
+            .unwrap();
+        // the room is full with the players who joined, so the cards are dealt
+        assert!(matches!(
+            reconfigure(&mut server, &room_id, Some(4), None, None).await,
+            Err(Error::RoomLocked)
+        ));
+        assert_eq!(
+            server.card_counts(&room_id).await.unwrap(),
+            vec![18, 17, 17]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reconfigure_room_with_teams() {
+        let mut server = Server::test_server(1);
+        let config =
+            serde_json::from_value(json!({ "players": 4, "decks": 1, "teams": [0, 1, 0, 1] }))
+                .unwrap();
+        let (room_id, _) = server.create_room(config, None).unwrap();
+        // the old teams do not cover the new seats
+        assert!(matches!(
+            reconfigure(&mut server, &room_id, Some(6), None, None).await,
+            Err(Error::InvalidTeams)
+        ));
+        assert!(matches!(
+            reconfigure(&mut server, &room_id, Some(6), None, Some(vec![0, 1, 0, 1])).await,
+            Err(Error::InvalidTeams)
+        ));
+        reconfigure(
+            &mut server,
+            &room_id,
+            Some(6),
+            None,
+            Some(vec![0, 1, 2, 0, 1, 2]),
+        )
+        .await
+        .unwrap();
+        // the teams can change on their own too
+        reconfigure(
+            &mut server,
+            &room_id,
+            None,
+            None,
+            Some(vec![0, 1, 0, 1, 0, 1]),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_out_of_turn_play_is_distinguished() {
+        let mut server = Server::test_server(1);
+        let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+        let config = serde_json::from_value(json!({ "players": 2, "decks": 1 })).unwrap();
+        let (room_id, _) = server.create_room(config, None).unwrap();
+        let state = server.game_state(&room_id).await.unwrap();
+        assert_eq!(state.current_player, None);
+        for _ in 0..2 {
+            server
+                .join(&room_id, None, None, &key_pair.secret)
+                .await
+                .unwrap();
+        }
+        let current = if server.my_turn(&room_id, 0).await.unwrap().is_my_turn {
+            0
+        } else {
+            1
+        };
+        let waiting = server.my_turn(&room_id, 1 - current).await.unwrap();
+        assert!(matches!(
+            server
+                .play(Action::Play(waiting.hand[0]), 1 - current, &room_id, None)
+                .await,
+            Err(Error::NotYourTurn)
+        ));
+        let turn = server.my_turn(&room_id, current).await.unwrap();
+        let unplayable = turn
+            .hand
+            .iter()
+            .find(|card| !turn.playable.contains(card))
+            .unwrap();
+        assert!(matches!(
+            server
+                .play(Action::Play(*unplayable), current, &room_id, None)
+                .await,
+            Err(Error::InvalidMove(_))
+        ));
+        let state = server.game_state(&room_id).await.unwrap();
+        assert_eq!(state.current_player, Some(current));
+    }
+
+    #[tokio::test]
+    async fn test_private_room_needs_password() {
+        let mut server = Server::test_server(1);
+        let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+        let config = serde_json::from_value(json!({ "players": 2, "decks": 1 })).unwrap();
+        let (room_id, _) = server.create_room(config, Some("hunter2")).unwrap();
+        for password in [None, Some("hunter3".to_string())] {
+            assert!(matches!(
+                server
+                    .join(&room_id, None, password, &key_pair.secret)
+                    .await,
+                Err(Error::WrongPassword)
+            ));
         }
+        let (_, player_id) = server
+            .join(&room_id, None, Some("hunter2".into()), &key_pair.secret)
+            .await
+            .unwrap();
+        assert_eq!(player_id, 0);
+    }
+
+    #[tokio::test]
+    async fn test_creator_takes_first_seat() {
+        let mut server = Server::test_server(2);
+        let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+        let config: RoomConfig =
+            serde_json::from_value(json!({ "players": 2, "decks": 1 })).unwrap();
+        let player_key = Some(Uuid::new_v4());
+        let seat = server
+            .create_and_join(config.clone(), None, player_key, &key_pair.secret)
+            .await
+            .unwrap();
+        assert_eq!(seat.player_id, 0);
+        let (_, player_id) = server
+            .join(&seat.room_id, None, None, &key_pair.secret)
+            .await
+            .unwrap();
+        assert_eq!(player_id, 1);
+
+        // a creator who cannot take the seat leaves no empty room behind
+        assert!(matches!(
+            server
+                .create_and_join(config, None, player_key, &key_pair.secret)
+                .await,
+            Err(Error::AlreadyInGame)
+        ));
+        assert_eq!(server.rooms.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_quickplay_fills_rooms() {
+        let mut server = Server::test_server(3);
+        let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+        let config: RoomConfig =
+            serde_json::from_value(json!({ "players": 2, "decks": 1 })).unwrap();
+
+        let first = server
+            .quickplay(config.clone(), None, &key_pair.secret)
+            .await
+            .unwrap();
+        assert_eq!(first.player_id, 0);
+        let second = server
+            .quickplay(config.clone(), None, &key_pair.secret)
+            .await
+            .unwrap();
+        assert_eq!(second.room_id, first.room_id);
+        assert_eq!(second.player_id, 1);
+
+        // the full room is no longer offered
+        let third = server
+            .quickplay(config.clone(), None, &key_pair.secret)
+            .await
+            .unwrap();
+        assert_ne!(third.room_id, first.room_id);
+        assert_eq!(third.player_id, 0);
+
+        // a room that fills up through a regular join is skipped
+        server
+            .join(&third.room_id, None, None, &key_pair.secret)
+            .await
+            .unwrap();
+        let fourth = server
+            .quickplay(config, None, &key_pair.secret)
+            .await
+            .unwrap();
+        assert_ne!(fourth.room_id, third.room_id);
+        assert_eq!(fourth.player_id, 0);
+        assert_eq!(server.rooms.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_quickplay_skips_reconfigured_rooms() {
+        let mut server = Server::test_server(3);
+        let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+        let config: RoomConfig =
+            serde_json::from_value(json!({ "players": 3, "decks": 1 })).unwrap();
+        let player_key = Some(Uuid::new_v4());
+
+        let first = server
+            .quickplay(config.clone(), player_key, &key_pair.secret)
+            .await
+            .unwrap();
+        reconfigure(&mut server, &first.room_id, Some(4), None, None)
+            .await
+            .unwrap();
+        let second = server
+            .quickplay(config.clone(), None, &key_pair.secret)
+            .await
+            .unwrap();
+        assert_ne!(second.room_id, first.room_id);
+        assert_eq!(second.player_id, 0);
+
+        // a player who cannot join leaves no empty room behind
+        reconfigure(&mut server, &second.room_id, Some(4), None, None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            server.quickplay(config, player_key, &key_pair.secret).await,
+            Err(Error::AlreadyInGame)
+        ));
+        assert_eq!(server.rooms.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_one_seat_per_player_key() {
+        let mut server = Server::test_server(2);
+        let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+        let config = serde_json::from_value(json!({ "players": 3, "decks": 1 })).unwrap();
+        let (first_room, _) = server.create_room(config, None).unwrap();
+        let config = serde_json::from_value(json!({ "players": 3, "decks": 1 })).unwrap();
+        let (second_room, _) = server.create_room(config, None).unwrap();
+        let player_key = Some(Uuid::new_v4());
+
+        server
+            .join(&first_room, player_key, None, &key_pair.secret)
+            .await
+            .unwrap();
+        assert!(matches!(
+            server
+                .join(&second_room, player_key, None, &key_pair.secret)
+                .await,
+            Err(Error::AlreadyInGame)
+        ));
+        assert!(matches!(
+            server
+                .join(&first_room, player_key, None, &key_pair.secret)
+                .await,
+            Err(Error::AlreadyInGame)
+        ));
+        // players without a key are not tracked
+        server
+            .join(&second_room, None, None, &key_pair.secret)
+            .await
+            .unwrap();
+        server
+            .join(&second_room, None, None, &key_pair.secret)
+            .await
+            .unwrap();
+
+        server.rooms.remove(&first_room);
+        server
+            .join(&second_room, player_key, None, &key_pair.secret)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_hidden_card_counts() {
+        let mut server = Server::test_server(2);
+        let config = serde_json::from_value(json!({ "players": 2, "decks": 1 })).unwrap();
+        let (open_room, _) = server.create_room(config, None).unwrap();
+        let config =
+            serde_json::from_value(json!({ "players": 2, "decks": 1, "hide_counts": true }))
+                .unwrap();
+        let (blind_room, _) = server.create_room(config, None).unwrap();
+
+        let state = server.game_state(&open_room).await.unwrap();
+        assert_eq!(state.card_counts, Some(vec![]));
+        assert!(server.card_counts(&open_room).await.is_ok());
+        let state = server.game_state(&blind_room).await.unwrap();
+        assert_eq!(state.card_counts, None);
+        assert!(matches!(
+            server.card_counts(&blind_room).await,
+            Err(Error::CountsHidden)
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_watchdog_removes_unresponsive_rooms() {
+        let mut server = Server::test_server(2);
+        let config = serde_json::from_value(json!({ "players": 2, "decks": 1 })).unwrap();
+        let (healthy_room, _) = server.create_room(config, None).unwrap();
+        // a room that never reads its messages
+        let (sender, _receiver) = mpsc::channel(1);
+        let stuck_room = Uuid::new_v4();
+        server.rooms.insert(stuck_room, sender);
+
+        let stuck = find_stuck_rooms(server.room_senders()).await;
+        assert_eq!(stuck, vec![stuck_room]);
+        server.remove_stuck_rooms(stuck);
+        assert!(server.rooms.contains_key(&healthy_room));
+        assert!(!server.rooms.contains_key(&stuck_room));
+    }
+
+    /// Insert a room into the `server` that reports `activity` when asked.
+    fn insert_room_with_activity(server: &mut Server, activity: Option<Instant>) -> Uuid {
+        let (sender, mut receiver) = mpsc::channel(1);
+        tokio::spawn(async move {
+            while let Some(msg) = receiver.recv().await {
+                if let ServerRoomMessage::Activity(responder) = msg {
+                    responder.send(activity).unwrap();
+                }
+            }
+        });
+        let room_id = Uuid::new_v4();
+        server.rooms.insert(room_id, sender);
+        room_id
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pick_active_room() {
+        let mut server = Server::test_server(4);
+        assert!(matches!(
+            pick_active_room(server.room_senders()).await,
+            Err(Error::NoActiveRoom)
+        ));
+
+        let now = Instant::now();
+        insert_room_with_activity(&mut server, None);
+        insert_room_with_activity(&mut server, Some(now));
+        let latest = insert_room_with_activity(&mut server, Some(now + Duration::from_secs(1)));
+        // a room that never answers is left out instead of holding up the pick
+        let (sender, _receiver) = mpsc::channel(1);
+        server.rooms.insert(Uuid::new_v4(), sender);
+        assert_eq!(
+            pick_active_room(server.room_senders()).await.unwrap(),
+            latest
+        );
+        assert_eq!(server.rooms.len(), 4);
     }
 }
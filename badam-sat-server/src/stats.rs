@@ -0,0 +1,213 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread::{self, JoinHandle},
+};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::SCHEMA_VERSION;
+
+/// Game results of a player across all the rooms they played in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PlayerStats {
+    games_played: usize,
+    wins: usize,
+    losses: usize,
+}
+
+/// Result of one player in one finished game, as stored on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+struct GameResult {
+    /// Results stored before the format was versioned are of the first
+    /// version.
+    #[serde(default = "first_schema_version")]
+    schema_version: u32,
+    player_key: Uuid,
+    won: bool,
+}
+
+fn first_schema_version() -> u32 {
+    1
+}
+
+/// Statistics of the players identified by a `player_key` when joining.
+///
+/// Every result is appended as a line of JSON to the file the store was
+/// opened with, and the statistics are rebuilt from the file on start up.
+/// The file is written on a thread of its own, so that a slow disk does not
+/// hold up the rooms or the server while they hold the store's lock.
+#[derive(Debug, Default)]
+pub struct StatsStore {
+    stats: HashMap<Uuid, PlayerStats>,
+    writer: Option<(mpsc::Sender<GameResult>, JoinHandle<()>)>,
+}
+
+impl StatsStore {
+    /// Open the store backed by the file at `path`, which is created when the
+    /// first result is recorded. Without a `path` the statistics are only
+    /// kept in memory.
+    ///
+    /// Unreadable files and lines are logged and skipped, so that a damaged
+    /// file does not keep the server from starting. So are results written
+    /// by a newer version of the server, whose format may have changed.
+    pub fn open(path: Option<PathBuf>) -> Self {
+        let mut store = StatsStore::default();
+        let Some(path) = path else {
+            return store;
+        };
+        let file = match File::open(&path) {
+            Ok(file) => Some(file),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                log::error!("could not read player stats from {}: {err}", path.display());
+                None
+            }
+        };
+        if let Some(file) = file {
+            store.load(file, &path);
+        }
+        let (sender, receiver) = mpsc::channel();
+        let writer = thread::spawn(move || write_results(&path, receiver));
+        store.writer = Some((sender, writer));
+        store
+    }
+
+    /// Add the results read from the `file` at `path`.
+    fn load(&mut self, file: File, path: &Path) {
+        let results: Vec<GameResult> = BufReader::new(file)
+            .lines()
+            .filter_map(|line| match line.map(|line| serde_json::from_str(&line)) {
+                Ok(Ok(GameResult { schema_version, .. })) if schema_version > SCHEMA_VERSION => {
+                    log::warn!(
+                        "skipping result of unsupported schema version {schema_version} in {}",
+                        path.display()
+                    );
+                    None
+                }
+                Ok(Ok(result)) => Some(result),
+                _ => {
+                    log::warn!("skipping unreadable line in {}", path.display());
+                    None
+                }
+            })
+            .collect();
+        for result in results {
+            self.add(result);
+        }
+    }
+
+    /// Record the end of a game for the `player_key`.
+    pub fn record(&mut self, player_key: Uuid, won: bool) {
+        let result = GameResult {
+            schema_version: SCHEMA_VERSION,
+            player_key,
+            won,
+        };
+        self.add(result);
+        if let Some((sender, _)) = &self.writer {
+            if sender.send(result).is_err() {
+                log::error!("player stats writer stopped, result not saved");
+            }
+        }
+    }
+
+    fn add(&mut self, result: GameResult) {
+        let stats = self.stats.entry(result.player_key).or_default();
+        stats.games_played += 1;
+        if result.won {
+            stats.wins += 1;
+        } else {
+            stats.losses += 1;
+        }
+    }
+
+    /// Get the statistics of the `player_key`, which are all zero for players
+    /// who have not finished a game yet.
+    pub fn get(&self, player_key: &Uuid) -> PlayerStats {
+        self.stats.get(player_key).copied().unwrap_or_default()
+    }
+}
+
+impl Drop for StatsStore {
+    /// Wait for the results recorded so far to be written.
+    fn drop(&mut self) {
+        if let Some((sender, writer)) = self.writer.take() {
+            drop(sender);
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Append every result received on the `receiver` to the file at `path`,
+/// until the store is dropped.
+fn write_results(path: &Path, receiver: mpsc::Receiver<GameResult>) {
+    for result in receiver {
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(&result)?));
+        if let Err(err) = written {
+            log::error!("could not write player stats to {}: {err}", path.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use uuid::Uuid;
+
+    use super::{PlayerStats, StatsStore};
+    use crate::SCHEMA_VERSION;
+
+    #[test]
+    fn test_stats_survive_reopening() {
+        let path = std::env::temp_dir().join(format!("badam-sat-stats-{}.jsonl", Uuid::new_v4()));
+        let (winner, loser) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut store = StatsStore::open(Some(path.clone()));
+        store.record(winner, true);
+        store.record(loser, false);
+        store.record(winner, false);
+        drop(store);
+
+        let store = StatsStore::open(Some(path.clone()));
+        fs::remove_file(path).unwrap();
+        assert_eq!(
+            store.get(&winner),
+            PlayerStats {
+                games_played: 2,
+                wins: 1,
+                losses: 1
+            }
+        );
+        assert_eq!(store.get(&loser).losses, 1);
+        assert_eq!(store.get(&Uuid::new_v4()), PlayerStats::default());
+    }
+
+    #[test]
+    fn test_future_schema_version_is_skipped() {
+        let path = std::env::temp_dir().join(format!("badam-sat-stats-{}.jsonl", Uuid::new_v4()));
+        let (old, future) = (Uuid::new_v4(), Uuid::new_v4());
+        let lines = [
+            // written before the format was versioned
+            format!(r#"{{"player_key":"{old}","won":true}}"#),
+            format!(
+                r#"{{"schema_version":{},"player_key":"{future}","won":true,"margin":3}}"#,
+                SCHEMA_VERSION + 1
+            ),
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        let store = StatsStore::open(Some(path.clone()));
+        fs::remove_file(path).unwrap();
+        assert_eq!(store.get(&old).wins, 1);
+        assert_eq!(store.get(&future), PlayerStats::default());
+    }
+}
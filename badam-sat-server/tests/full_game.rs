@@ -0,0 +1,265 @@
+use axum::{
+    body::Body,
+    http::{
+        header::{
+            ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_ORIGIN,
+            ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, AUTHORIZATION,
+            CONTENT_TYPE, ORIGIN,
+        },
+        Request, StatusCode,
+    },
+    Router,
+};
+use badam_sat_protocol::{JoinSuccess, NewRoomResponse, Role};
+use badam_sat_server::{badam_sat_router, cors_layer, SCHEMA_VERSION};
+use pasetors::{
+    keys::{AsymmetricKeyPair, Generate},
+    version4::V4,
+};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+/// Send a request to the `router` as the player with the `token`, if any,
+/// returning the status and the JSON body of the response.
+async fn send(
+    router: &Router,
+    method: &str,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+    }
+    let request = match body {
+        Some(body) => request
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let body = if bytes.is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_slice(&bytes).unwrap()
+    };
+    (status, body)
+}
+
+/// Create a room for four players and join all of them, returning the room id
+/// and the token of each seat.
+async fn set_up_room(router: &Router) -> (Uuid, Vec<String>) {
+    let (status, body) = send(
+        router,
+        "POST",
+        "/api/create_room",
+        None,
+        Some(json!({ "players": 4, "decks": 1 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let room: NewRoomResponse = serde_json::from_value(body).unwrap();
+
+    let mut tokens = vec![String::new(); 4];
+    for _ in 0..4 {
+        let (status, body) = send(
+            router,
+            "POST",
+            "/api/join",
+            None,
+            Some(json!({ "room_id": room.room_id })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let joined: JoinSuccess = serde_json::from_value(body).unwrap();
+        tokens[joined.player_id.unwrap()] = joined.token.unwrap();
+    }
+    (room.room_id, tokens)
+}
+
+#[tokio::test]
+async fn test_four_players_finish_a_game() {
+    let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+    let router = badam_sat_router(key_pair, 1, None, None::<&str>, None);
+    let (room_id, tokens) = set_up_room(&router).await;
+
+    let mut moves = 0;
+    loop {
+        let (status, turn_order) = send(
+            &router,
+            "GET",
+            &format!("/api/turn_order?room_id={room_id}"),
+            None,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let Some(player) = turn_order["current"].as_u64() else {
+            break;
+        };
+        let token = tokens[player as usize].as_str();
+
+        // play the suggested card, which is always legal, or pass
+        let (status, hint) = send(&router, "GET", "/api/hint", Some(token), None).await;
+        let (_, turn) = send(&router, "GET", "/api/my_turn", Some(token), None).await;
+        assert_eq!(turn["is_my_turn"], json!(true));
+        assert_eq!(turn["current_player"], json!(player));
+        let action = match status {
+            StatusCode::OK => {
+                assert!(turn["playable"].as_array().unwrap().contains(&hint));
+                assert_eq!(turn["can_pass"], json!(false));
+                json!({ "Play": hint })
+            }
+            StatusCode::NO_CONTENT => {
+                assert_eq!(turn["can_pass"], json!(true));
+                json!("Pass")
+            }
+            status => panic!("unexpected hint status {status}"),
+        };
+        let (status, outcome) = send(&router, "POST", "/api/play", Some(token), Some(action)).await;
+        assert_eq!(status, StatusCode::OK, "move rejected: {outcome}");
+
+        moves += 1;
+        assert!(moves < 1000, "game did not finish");
+    }
+
+    let (status, winner) = send(
+        &router,
+        "GET",
+        &format!("/api/winner?room_id={room_id}"),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(winner["end_reason"], json!("PlayerEmptied"));
+    let winner = winner["winner"].as_u64().expect("game has a winner") as usize;
+
+    // the room has no turn timer, and the game is over anyway
+    let (status, timer) = send(
+        &router,
+        "GET",
+        &format!("/api/turn_timer?room_id={room_id}"),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(timer, Value::Null);
+
+    let (status, hand) = send(&router, "GET", "/api/my_hand", Some(&tokens[winner]), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(hand, json!([]));
+
+    let (status, export) = send(
+        &router,
+        "GET",
+        &format!("/api/export?room_id={room_id}"),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(export["schema_version"], json!(SCHEMA_VERSION));
+    assert_eq!(export["finishing_order"][0], json!(winner));
+    assert_eq!(export["scores"].as_array().unwrap().len(), 4);
+    assert_eq!(export["moves"].as_array().unwrap().len(), moves + 1);
+}
+
+#[tokio::test]
+async fn test_public_key_is_exposed() {
+    let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+    let router = badam_sat_router(key_pair, 1, None, None::<&str>, None);
+    let (status, body) = send(&router, "GET", "/api/public_key", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["paserk"].as_str().unwrap().starts_with("k4.public."));
+}
+
+#[tokio::test]
+async fn test_full_room_offers_spectating() {
+    let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+    let router = badam_sat_router(key_pair, 1, None, None::<&str>, None);
+    let (room_id, _) = set_up_room(&router).await;
+
+    let (status, _) = send(
+        &router,
+        "POST",
+        "/api/join",
+        None,
+        Some(json!({ "room_id": room_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, body) = send(
+        &router,
+        "POST",
+        "/api/join",
+        None,
+        Some(json!({ "room_id": room_id, "spectate_if_full": true })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let joined: JoinSuccess = serde_json::from_value(body).unwrap();
+    assert_eq!(joined.role, Role::Spectator);
+    assert_eq!(joined.player_id, None);
+    // spectators watch by room id, so they get no token
+    assert_eq!(joined.token, None);
+}
+
+#[tokio::test]
+async fn test_cors_keeps_frontend_fallback() {
+    let frontend = std::env::temp_dir().join(format!("badam-sat-frontend-{}", Uuid::new_v4()));
+    std::fs::create_dir(&frontend).unwrap();
+    std::fs::write(frontend.join("index.html"), "<html></html>").unwrap();
+    let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+    let router = badam_sat_router(key_pair, 1, None, Some(&frontend), None)
+        .layer(cors_layer("https://example.com").unwrap());
+
+    let request = Request::builder()
+        .uri("/index.html")
+        .header(ORIGIN, "https://example.com")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.clone().oneshot(request).await.unwrap();
+    std::fs::remove_dir_all(&frontend).unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+        "https://example.com"
+    );
+
+    let preflight = Request::builder()
+        .method("OPTIONS")
+        .uri("/api/play")
+        .header(ORIGIN, "https://example.com")
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .header(ACCESS_CONTROL_REQUEST_HEADERS, "authorization")
+        .body(Body::empty())
+        .unwrap();
+    let response = router.oneshot(preflight).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response
+        .headers()
+        .contains_key(ACCESS_CONTROL_ALLOW_HEADERS));
+}
+
+#[tokio::test]
+async fn test_me_reports_the_seat_of_the_token() {
+    let key_pair = AsymmetricKeyPair::<V4>::generate().unwrap();
+    let router = badam_sat_router(key_pair, 1, None, None::<&str>, None);
+    let (room_id, tokens) = set_up_room(&router).await;
+
+    for (player, token) in tokens.iter().enumerate() {
+        let (status, me) = send(&router, "GET", "/api/me", Some(token), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(me, json!({ "player_id": player, "room_id": room_id }));
+    }
+    let (status, _) = send(&router, "GET", "/api/me", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -1,6 +1,9 @@
 use card_deck::standard_deck::{Card, Rank, StandardDeckBuilder, Suit};
-use rand::thread_rng;
-use std::collections::HashSet;
+use rand::{rngs::StdRng, seq::SliceRandom, thread_rng, Rng, SeedableRng};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, iter,
+};
 
 use crate::players::Player;
 
@@ -11,7 +14,43 @@ pub struct BadamSat {
     players: Vec<Player>,
     playing_area: PlayingArea,
     decks: usize,
+    jokers: usize,
+    wild_sevens: bool,
+    hand_size: Option<usize>,
+    seed: Option<[u8; 32]>,
+    allow_voluntary_pass: bool,
+    completion_bonus: usize,
+    leftover_policy: LeftoverPolicy,
+    autoseed_sevens: bool,
+    special_cards: bool,
+    play_until_last: bool,
+    max_passes: Option<usize>,
+    direction: Direction,
+    skip_next: bool,
+    finished: Vec<usize>,
+    /// Turns passed or skipped in a row by players who had nothing to play.
+    passes_without_play: usize,
+    /// Number of times each player has passed while they could have played.
+    passes: Vec<usize>,
     player_count: usize,
+    hand_sizes: Vec<usize>,
+    deal_order: Vec<(usize, Card)>,
+    /// Cards left over after dealing, drawn from the end.
+    stock: Vec<Card>,
+    history: Vec<Transition>,
+    completion_credits: HashMap<Suit, usize>,
+    observer: Option<Observer>,
+}
+
+/// Callback told about every transition applied to a [`BadamSat`].
+pub type TransitionObserver = Box<dyn FnMut(&Transition, &BadamSat) + Send>;
+
+struct Observer(TransitionObserver);
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Observer")
+    }
 }
 
 /// State of the [`BadamSat`].
@@ -24,6 +63,7 @@ enum GameState {
     },
     Over {
         winner: usize,
+        reason: EndReason,
     },
 }
 
@@ -33,7 +73,13 @@ enum GameState {
 pub enum Transition {
     DealCards,
     Play { player: usize, card: Card },
+    PlayJoker { player: usize, substitute: Card },
     Pass { player: usize },
+    Draw { player: usize },
+    SkipTurn { player: usize },
+    SitOut { player: usize },
+    EndGame,
+    Forfeit { winner: usize },
 }
 
 /// Played [`Card`]s in a game.
@@ -54,15 +100,134 @@ impl PlayingArea {
         PlayingArea { card_stacks }
     }
 
-    /// Try to play a [`Card`].
-    fn try_play(&mut self, card: Card) -> Result<(), InvalidPlay> {
+    /// Create a `PlayingArea` with a stack for each of the `states`, in order,
+    /// for example to compare against the playing area of a game in tests.
+    ///
+    /// A game keeps its stacks ordered by suit as in [`Suit::all_suits`],
+    /// with one stack per deck for each suit.
+    pub fn from_states(states: Vec<(Suit, StackState)>) -> Self {
+        let card_stacks = states
+            .into_iter()
+            .map(|(suit, stack_state)| CardStack {
+                stack_state,
+                ..CardStack::new(suit)
+            })
+            .collect();
+        PlayingArea { card_stacks }
+    }
+
+    /// Get the cards that would connect to a stack, regardless of who holds
+    /// them.
+    fn open_slots(&self) -> HashSet<Card> {
+        self.card_stacks
+            .iter()
+            .flat_map(|stack| {
+                let mut cards = HashSet::with_capacity(2);
+                match stack.stack_state {
+                    StackState::Empty => {
+                        cards.insert(Card::new_normal(stack.suit, Rank::new(7)));
+                    }
+                    StackState::SevenOnly => {
+                        cards.insert(Card::new_normal(stack.suit, Rank::new(8)));
+                        cards.insert(Card::new_normal(stack.suit, Rank::new(6)));
+                    }
+                    StackState::LowOnly(card) => {
+                        cards.insert(Card::new_normal(stack.suit, Rank::new(8)));
+                        if card.rank().unwrap().value() != 1 {
+                            cards.insert(Card::new_normal(
+                                stack.suit,
+                                Rank::new(card.rank().unwrap().value() - 1),
+                            ));
+                        }
+                    }
+                    StackState::HighOnly(card) => {
+                        cards.insert(Card::new_normal(stack.suit, Rank::new(6)));
+                        if card.rank().unwrap().value() != 13 {
+                            cards.insert(Card::new_normal(
+                                stack.suit,
+                                Rank::new(card.rank().unwrap().value() + 1),
+                            ));
+                        }
+                    }
+                    StackState::LowAndHigh { low, high } => {
+                        if low.rank().unwrap().value() != 1 {
+                            cards.insert(Card::new_normal(
+                                stack.suit,
+                                Rank::new(low.rank().unwrap().value() - 1),
+                            ));
+                        }
+                        if high.rank().unwrap().value() != 13 {
+                            cards.insert(Card::new_normal(
+                                stack.suit,
+                                Rank::new(high.rank().unwrap().value() + 1),
+                            ));
+                        }
+                    }
+                }
+                cards
+            })
+            .collect()
+    }
+
+    /// Try to play a [`Card`], returning the index of the stack it was
+    /// placed on.
+    fn try_play(&mut self, card: Card) -> Result<usize, InvalidPlay> {
+        let (idx, new_stack) = self.check_play(card)?;
+        self.card_stacks[idx] = new_stack;
+        Ok(idx)
+    }
+
+    /// Check where a [`Card`] would be played without modifying the playing
+    /// area.
+    ///
+    /// Returns the index of the accepting stack and its new state. If no stack
+    /// accepts the card, the most specific reason among all the stacks is
+    /// returned.
+    fn check_play(&self, card: Card) -> Result<(usize, CardStack), InvalidPlay> {
+        let mut reason = InvalidPlay::SuitMismatch;
+        for (idx, stack) in self.card_stacks.iter().enumerate() {
+            match stack.add(card) {
+                Ok(new_stack) => return Ok((idx, new_stack)),
+                Err(err) => reason = reason.most_specific(err),
+            }
+        }
+        Err(reason)
+    }
+
+    /// Get the index of the stack that would accept the `card`, the same one
+    /// that playing it would place it on.
+    pub fn placement_for(&self, card: Card) -> Option<usize> {
+        self.check_play(card).ok().map(|(idx, _)| idx)
+    }
+
+    /// Try to play a joker in place of the `substitute` [`Card`], returning
+    /// the index of the stack it was placed on.
+    fn try_play_joker(&mut self, substitute: Card) -> Result<usize, InvalidPlay> {
+        let (idx, mut new_stack) = self.check_play(substitute)?;
+        new_stack.jokers.push(substitute);
+        self.card_stacks[idx] = new_stack;
+        Ok(idx)
+    }
+
+    /// Replace a joker standing in for `card` with the actual `card`.
+    ///
+    /// Returns `false` if no joker is standing in for `card`.
+    fn replace_joker(&mut self, card: &Card) -> bool {
         for stack in self.card_stacks.iter_mut() {
-            if let Ok(new_stack) = stack.add(card) {
-                *stack = new_stack;
-                return Ok(());
+            if let Some(idx) = stack.jokers.iter().position(|joker| joker == card) {
+                stack.jokers.swap_remove(idx);
+                return true;
             }
         }
-        Err(InvalidPlay::CardMismatch)
+        false
+    }
+
+    /// Take all the cards off the playing area, keeping its stacks.
+    pub fn reset(&mut self) {
+        for stack in self.card_stacks.iter_mut() {
+            stack.stack_state = StackState::Empty;
+            stack.jokers.clear();
+        }
     }
 
     fn is_empty(&self) -> bool {
@@ -75,22 +240,74 @@ impl PlayingArea {
     pub fn stacks(&self) -> &[CardStack] {
         &self.card_stacks
     }
+
+    /// Get the cards still needed to complete the stacks of each suit, from
+    /// the ace up to the king. Cards that a joker stands in for are still
+    /// needed. With several decks a card is listed once for every stack
+    /// missing it.
+    pub fn missing_cards(&self) -> HashMap<Suit, Vec<Card>> {
+        let mut missing: HashMap<Suit, Vec<Card>> = HashMap::new();
+        for stack in &self.card_stacks {
+            let ranks: Vec<u8> = match &stack.stack_state {
+                StackState::Empty => (1..=13).collect(),
+                StackState::SevenOnly => (1..7).chain(8..=13).collect(),
+                StackState::LowOnly(low) => {
+                    (1..low.rank().unwrap().value()).chain(8..=13).collect()
+                }
+                StackState::HighOnly(high) => (1..7)
+                    .chain(high.rank().unwrap().value() + 1..=13)
+                    .collect(),
+                StackState::LowAndHigh { low, high } => (1..low.rank().unwrap().value())
+                    .chain(high.rank().unwrap().value() + 1..=13)
+                    .collect(),
+            };
+            missing.entry(stack.suit).or_default().extend(
+                ranks
+                    .into_iter()
+                    .map(|rank| Card::new_normal(stack.suit, Rank::new(rank)))
+                    .chain(stack.jokers.iter().cloned()),
+            );
+        }
+        missing
+    }
 }
 
-#[derive(Debug, thiserror::Error)]
-#[error("played card cannot be added to the playing area")]
-enum InvalidPlay {
+/// Reasons why a [`Card`] cannot be added to the [`PlayingArea`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvalidPlay {
+    #[error("all stacks of the card's suit are full")]
     StackFull,
+    #[error("card's suit does not match any stack")]
     SuitMismatch,
+    #[error("card's rank does not connect to any stack")]
     RankMismatch,
-    CardMismatch,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl InvalidPlay {
+    /// Pick the more informative of two reasons.
+    ///
+    /// A rank mismatch means some stack of the suit could still accept other
+    /// cards, so it is preferred over the stack being full, which in turn is
+    /// preferred over the suit not matching at all.
+    fn most_specific(self, other: InvalidPlay) -> InvalidPlay {
+        match (self, other) {
+            (InvalidPlay::RankMismatch, _) | (_, InvalidPlay::RankMismatch) => {
+                InvalidPlay::RankMismatch
+            }
+            (InvalidPlay::StackFull, _) | (_, InvalidPlay::StackFull) => InvalidPlay::StackFull,
+            _ => InvalidPlay::SuitMismatch,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CardStack {
     suit: Suit,
     stack_state: StackState,
+    #[cfg_attr(feature = "serde", serde(default))]
+    jokers: Vec<Card>,
 }
 
 /// Played cards belonging to a single [`Suit`].
@@ -110,18 +327,23 @@ impl CardStack {
         CardStack {
             suit,
             stack_state: StackState::Empty,
+            jokers: Vec::new(),
         }
     }
 
     /// Create a new stack for `suit` cards with the initial `stack_state`.
     fn new_with_stack_state(suit: Suit, stack_state: StackState) -> Self {
-        CardStack { suit, stack_state }
+        CardStack {
+            suit,
+            stack_state,
+            jokers: Vec::new(),
+        }
     }
 
     /// Add a card to the stack.
     fn add(&self, card: Card) -> Result<Self, InvalidPlay> {
-        match (&self.suit, card.suit().unwrap()) {
-            (s1, s2) if s1 == s2 => match &self.stack_state {
+        let new_stack = match (&self.suit, card.suit()) {
+            (s1, Some(s2)) if s1 == s2 => match &self.stack_state {
                 StackState::Empty => {
                     if card.rank().unwrap().value() == 7 {
                         Ok(CardStack::new_with_stack_state(
@@ -210,7 +432,11 @@ impl CardStack {
                 }
             },
             _ => Err(InvalidPlay::SuitMismatch),
-        }
+        };
+        new_stack.map(|stack| CardStack {
+            jokers: self.jokers.clone(),
+            ..stack
+        })
     }
 
     /// Get the suit of the stack.
@@ -222,44 +448,452 @@ impl CardStack {
     pub fn stack_state(&self) -> &StackState {
         &self.stack_state
     }
+
+    /// Get the cards missing from `available` that are needed to connect
+    /// `card` to the stack.
+    ///
+    /// Returns `None` if `card` can never be added to the stack.
+    fn missing_cards_to(&self, card: &Card, available: &HashSet<Card>) -> Option<Vec<Card>> {
+        if card.suit() != Some(&self.suit) {
+            return None;
+        }
+        let rank = card.rank().unwrap().value();
+        let (low, high) = match &self.stack_state {
+            // an empty stack needs the 7 before anything else
+            StackState::Empty => (8, 6),
+            StackState::SevenOnly => (7, 7),
+            StackState::LowOnly(low) => (low.rank().unwrap().value(), 7),
+            StackState::HighOnly(high) => (7, high.rank().unwrap().value()),
+            StackState::LowAndHigh { low, high } => {
+                (low.rank().unwrap().value(), high.rank().unwrap().value())
+            }
+        };
+        let gap = if rank < low {
+            (rank + 1)..low
+        } else if rank > high {
+            (high + 1)..rank
+        } else {
+            return None;
+        };
+        Some(
+            gap.map(|rank| Card::new_normal(self.suit, Rank::new(rank)))
+                .filter(|card| !available.contains(card))
+                .collect(),
+        )
+    }
+
+    /// Count the cards on the stack, including those represented by jokers.
+    fn len(&self) -> usize {
+        let value = |card: &Card| card.rank().unwrap().value() as usize;
+        match &self.stack_state {
+            StackState::Empty => 0,
+            StackState::SevenOnly => 1,
+            StackState::LowOnly(low) => 8 - value(low),
+            StackState::HighOnly(high) => value(high) - 6,
+            StackState::LowAndHigh { low, high } => value(high) - value(low) + 1,
+        }
+    }
+
+    /// Check whether the stack runs all the way from the ace to the king.
+    pub fn is_complete(&self) -> bool {
+        matches!(
+            self.stack_state,
+            StackState::LowAndHigh { low, high }
+                if low.rank() == Some(&Rank::Ace) && high.rank() == Some(&Rank::King)
+        )
+    }
+
+    /// Get the cards of the stack that are currently represented by jokers.
+    pub fn jokers(&self) -> &[Card] {
+        &self.jokers
+    }
 }
 
-impl BadamSat {
-    /// Create a game of बदाम सात (Badam Sat) for `players` number of players
-    /// played with `decks` number of decks.
-    pub fn with_player_and_deck_capacity(players: usize, decks: usize) -> Self {
-        let num_cards = decks * 52;
+/// Way the turns go around the table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Direction {
+    /// From each seat to the next higher one.
+    #[default]
+    Clockwise,
+    /// From each seat to the next lower one.
+    Counterclockwise,
+}
+
+/// How a game of [`BadamSat`] came to an end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EndReason {
+    /// A player ran out of cards, or with [`BadamSatBuilder::play_until_last`]
+    /// all but one did.
+    PlayerEmptied,
+    /// Nobody could play any of the cards left.
+    Stalemate,
+    /// The game was cut short with [`Transition::EndGame`], for example
+    /// because it ran out of time.
+    Timeout,
+    /// Every player but the winner gave up, with [`Transition::Forfeit`].
+    AllForfeited,
+}
+
+/// Which players get the extra cards when the cards cannot be dealt evenly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LeftoverPolicy {
+    /// The players in the lowest seats get one extra card each.
+    #[default]
+    EarlySeats,
+    /// The players in the highest seats get one extra card each.
+    LateSeats,
+    /// Randomly chosen players get one extra card each. The choice follows
+    /// the game's seed, if it has one.
+    Random,
+}
+
+impl LeftoverPolicy {
+    /// Get the number of cards each of the `players` is dealt out of
+    /// `num_cards` cards.
+    fn hand_sizes(self, num_cards: usize, players: usize, seed: Option<[u8; 32]>) -> Vec<usize> {
         let (cards_per_player, leftover) = (num_cards / players, num_cards % players);
-        // assign cards_per_player + 1 card for every leftover card to leftover number of players
-        // then assign cards_per_player to the remaining players
-        let player_vec = (0..leftover)
-            .map(|_| Player::with_capacity(cards_per_player + 1))
-            .chain((leftover..players).map(|_| Player::with_capacity(cards_per_player)))
+        let mut sizes = vec![cards_per_player; players];
+        let extra_seats: Vec<usize> = match self {
+            LeftoverPolicy::EarlySeats => (0..leftover).collect(),
+            LeftoverPolicy::LateSeats => (players - leftover..players).collect(),
+            LeftoverPolicy::Random => {
+                let mut seats: Vec<usize> = (0..players).collect();
+                match seed {
+                    Some(seed) => seats.shuffle(&mut StdRng::from_seed(seed)),
+                    None => seats.shuffle(&mut thread_rng()),
+                }
+                seats.truncate(leftover);
+                seats
+            }
+        };
+        for seat in extra_seats {
+            sizes[seat] += 1;
+        }
+        sizes
+    }
+}
+
+/// Builder for a [`BadamSat`] game with optional rule variants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BadamSatBuilder {
+    players: usize,
+    decks: usize,
+    jokers: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    wild_sevens: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    hand_size: Option<usize>,
+    seed: Option<[u8; 32]>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    allow_voluntary_pass: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    completion_bonus: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    leftover_policy: LeftoverPolicy,
+    #[cfg_attr(feature = "serde", serde(default))]
+    autoseed_sevens: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    special_cards: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    play_until_last: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    max_passes: Option<usize>,
+}
+
+impl BadamSatBuilder {
+    /// Create a builder for a game of `players` number of players played with
+    /// `decks` number of decks.
+    pub fn new(players: usize, decks: usize) -> Self {
+        BadamSatBuilder {
+            players,
+            decks,
+            jokers: 0,
+            wild_sevens: false,
+            hand_size: None,
+            seed: None,
+            allow_voluntary_pass: false,
+            completion_bonus: 0,
+            leftover_policy: LeftoverPolicy::EarlySeats,
+            autoseed_sevens: false,
+            special_cards: false,
+            play_until_last: false,
+            max_passes: None,
+        }
+    }
+
+    /// Add `jokers` number of jokers to the cards being dealt.
+    ///
+    /// A joker can be played in place of any card that connects to a stack.
+    /// Whoever later plays the actual card takes the joker back into their
+    /// hand.
+    pub fn jokers(mut self, jokers: usize) -> Self {
+        self.jokers = jokers;
+        self
+    }
+
+    /// Only let jokers stand in for sevens, making them wild cards for
+    /// opening a stack of any suit. Has no effect without
+    /// [`BadamSatBuilder::jokers`].
+    pub fn wild_sevens(mut self, wild_sevens: bool) -> Self {
+        self.wild_sevens = wild_sevens;
+        self
+    }
+
+    /// Deal only `hand_size` cards to each player instead of the whole deck,
+    /// keeping the rest face down as a stock. A player with nothing to play
+    /// draws a card from the stock, which ends their turn, and can only pass
+    /// once the stock runs out. Players get fewer cards if there are not
+    /// enough to go around. `None`, the default, deals every card.
+    pub fn hand_size(mut self, hand_size: Option<usize>) -> Self {
+        self.hand_size = hand_size;
+        self
+    }
+
+    /// Shuffle the cards with a random number generator seeded with `seed`,
+    /// making the deal reproducible.
+    pub fn seed(mut self, seed: [u8; 32]) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Take `points` off the score of whoever completes a suit by playing its
+    /// ace or king. No bonus is given by default.
+    pub fn completion_bonus(mut self, points: usize) -> Self {
+        self.completion_bonus = points;
+        self
+    }
+
+    /// Choose which players get the extra cards when the cards cannot be
+    /// dealt evenly. The lowest seats get them by default.
+    pub fn leftover_policy(mut self, policy: LeftoverPolicy) -> Self {
+        self.leftover_policy = policy;
+        self
+    }
+
+    /// Place every seven on its stack right after dealing, so that play can
+    /// start on either side of any suit. The player in the first seat then
+    /// takes the first turn.
+    pub fn autoseed_sevens(mut self, autoseed: bool) -> Self {
+        self.autoseed_sevens = autoseed;
+        self
+    }
+
+    /// Make playing an 8 reverse the direction of the turns, and playing a 6
+    /// skip the next player. Jokers standing in for them have no effect.
+    pub fn special_cards(mut self, special: bool) -> Self {
+        self.special_cards = special;
+        self
+    }
+
+    /// Keep the game going after the first player runs out of cards, until at
+    /// most one player is left holding any. Players who are out have their
+    /// turns skipped. The first player out still wins.
+    pub fn play_until_last(mut self, play_until_last: bool) -> Self {
+        self.play_until_last = play_until_last;
+        self
+    }
+
+    /// Limit how many times each player may pass while they have a card they
+    /// could play. Once a player has passed `max_passes` times they must play
+    /// when they can. Passing with nothing to play is always allowed. Only has
+    /// an effect together with [`BadamSatBuilder::allow_voluntary_pass`].
+    pub fn max_passes(mut self, max_passes: Option<usize>) -> Self {
+        self.max_passes = max_passes;
+        self
+    }
+
+    /// Check whether the cards to be dealt include the opening card, the 7 of
+    /// hearts. Without it nobody can ever make the first move.
+    pub fn has_opening_card(&self) -> bool {
+        cards_to_deal(self.decks, self.jokers)
+            .contains(&Card::new_normal(Suit::Hearts, Rank::new(7)))
+    }
+
+    /// Let players pass even when they have a card they could play, which is
+    /// friendlier to beginners.
+    pub fn allow_voluntary_pass(mut self, allow: bool) -> Self {
+        self.allow_voluntary_pass = allow;
+        self
+    }
+
+    /// Check whether every player gets the same number of cards.
+    pub fn deals_evenly(&self) -> bool {
+        self.players > 0
+            && (self.hand_size.is_some()
+                || (self.decks * 52 + self.jokers).is_multiple_of(self.players))
+    }
+
+    /// Build the game.
+    pub fn build(self) -> BadamSat {
+        let players = self.players;
+        let num_cards = self.decks * 52 + self.jokers;
+        let hand_sizes = match self.hand_size {
+            Some(hand_size) => vec![hand_size.min(num_cards / players.max(1)); players],
+            None => self
+                .leftover_policy
+                .hand_sizes(num_cards, players, self.seed),
+        };
+        let player_vec = hand_sizes
+            .iter()
+            .map(|size| Player::with_capacity(*size))
             .collect();
         BadamSat {
             state: GameState::PrePlay,
             players: player_vec,
-            playing_area: PlayingArea::with_deck_capacity(decks),
-            decks,
+            playing_area: PlayingArea::with_deck_capacity(self.decks),
+            decks: self.decks,
+            jokers: self.jokers,
+            wild_sevens: self.wild_sevens,
+            hand_size: self.hand_size,
+            seed: self.seed,
+            allow_voluntary_pass: self.allow_voluntary_pass,
+            completion_bonus: self.completion_bonus,
+            leftover_policy: self.leftover_policy,
+            autoseed_sevens: self.autoseed_sevens,
+            special_cards: self.special_cards,
+            play_until_last: self.play_until_last,
+            max_passes: self.max_passes,
+            direction: Direction::Clockwise,
+            skip_next: false,
+            finished: Vec::new(),
+            passes_without_play: 0,
+            passes: vec![0; players],
             player_count: players,
+            hand_sizes,
+            deal_order: Vec::new(),
+            stock: Vec::new(),
+            history: Vec::new(),
+            completion_credits: HashMap::new(),
+            observer: None,
+        }
+    }
+}
+
+impl BadamSat {
+    /// Create a game of बदाम सात (Badam Sat) for `players` number of players
+    /// played with `decks` number of decks.
+    pub fn with_player_and_deck_capacity(players: usize, decks: usize) -> Self {
+        BadamSatBuilder::new(players, decks).build()
+    }
+
+    /// Create a game from already dealt `hands`, one for each player, played
+    /// with `decks` number of decks. The game skips shuffling and dealing and
+    /// starts directly in play.
+    ///
+    /// Any jokers in the hands are included in the game. Returns an error if
+    /// the hands do not make up exactly `decks` number of complete decks.
+    pub fn from_hands(hands: Vec<Vec<Card>>, decks: usize) -> Result<Self, InvalidHands> {
+        fn card_counts<'a>(cards: impl Iterator<Item = &'a Card>) -> HashMap<Card, usize> {
+            let mut counts = HashMap::new();
+            for card in cards {
+                *counts.entry(*card).or_default() += 1;
+            }
+            counts
+        }
+
+        let mut counts = card_counts(hands.iter().flatten());
+        let jokers = counts.remove(&Card::new_joker()).unwrap_or(0);
+        let deck = StandardDeckBuilder::new().subdecks(decks).build();
+        let mut expected_counts = card_counts(deck.iter());
+        expected_counts.remove(&Card::new_joker());
+        if hands.is_empty() || counts != expected_counts {
+            return Err(InvalidHands);
+        }
+        let mut game = BadamSatBuilder::new(hands.len(), decks)
+            .jokers(jokers)
+            .build();
+        game.start_with_hands(hands);
+        Ok(game)
+    }
+
+    /// Give the players the `hands` as if they were just dealt and start the
+    /// game, skipping shuffling and dealing.
+    fn start_with_hands(&mut self, hands: Vec<Vec<Card>>) {
+        self.deal_order = hands
+            .iter()
+            .enumerate()
+            .flat_map(|(player, hand)| hand.iter().map(move |card| (player, *card)))
+            .collect();
+        self.players = hands.into_iter().map(Player::new_with_hand).collect();
+        self.hand_sizes = self.players.iter().map(Player::hand_len).collect();
+        self.state = GameState::InPlay {
+            player: self.opening_player(),
+            valid_actions: self
+                .find_valid_actions()
+                .expect("in pre-play stage there must be at least one valid action after dealing"),
+        };
+    }
+
+    /// Build a game from `config` and apply the recorded `moves` to it in
+    /// order, for example to validate an exported game.
+    ///
+    /// The game can only be reproduced faithfully if `config` has a seed.
+    /// Returns the index of the first move that could not be applied, if any.
+    pub fn replay(config: BadamSatBuilder, moves: &[Transition]) -> Result<Self, InvalidReplay> {
+        let mut game = config.build();
+        for (index, action) in moves.iter().enumerate() {
+            game.update(action.clone())
+                .map_err(|reason| InvalidReplay { index, reason })?;
+        }
+        Ok(game)
+    }
+
+    /// Get the configuration this game was built with.
+    pub fn config(&self) -> BadamSatBuilder {
+        BadamSatBuilder {
+            players: self.player_count,
+            decks: self.decks,
+            jokers: self.jokers,
+            wild_sevens: self.wild_sevens,
+            hand_size: self.hand_size,
+            seed: self.seed,
+            allow_voluntary_pass: self.allow_voluntary_pass,
+            completion_bonus: self.completion_bonus,
+            leftover_policy: self.leftover_policy,
+            autoseed_sevens: self.autoseed_sevens,
+            special_cards: self.special_cards,
+            play_until_last: self.play_until_last,
+            max_passes: self.max_passes,
         }
     }
 
     /// Attempt to advance the game with the `action`.
     pub fn update(&mut self, action: Transition) -> Result<(), InvalidTransition> {
-        match (&self.state, &action) {
+        self.apply_transition(&action)?;
+        self.history.push(action);
+        if let Some(mut observer) = self.observer.take() {
+            (observer.0)(self.history.last().unwrap(), self);
+            self.observer = Some(observer);
+        }
+        Ok(())
+    }
+
+    /// Call the `observer` with every transition successfully applied from
+    /// now on, along with the game as it is right after the transition, for
+    /// reacting to plays without polling. Replaces any earlier observer.
+    pub fn on_transition(&mut self, observer: TransitionObserver) {
+        self.observer = Some(Observer(observer));
+    }
+
+    fn apply_transition(&mut self, action: &Transition) -> Result<(), InvalidTransition> {
+        match (&self.state, action) {
             (GameState::PrePlay, Transition::DealCards) => {
                 self.deal();
                 self.state = GameState::InPlay {
-                    player: 0,
+                    player: self.opening_player(),
                     valid_actions: self.find_valid_actions().expect(
                         "in pre-play stage there must be at least one valid action after dealing",
                     ),
                 };
                 Ok(())
             }
-            (GameState::PrePlay, _) => Err(InvalidTransition),
-            (GameState::InPlay { .. }, Transition::DealCards) => Err(InvalidTransition),
+            (GameState::PrePlay, _) => Err(InvalidTransition::NotAllowed),
+            (GameState::InPlay { .. }, Transition::DealCards) => Err(InvalidTransition::NotAllowed),
             (
                 GameState::InPlay {
                     player,
@@ -270,18 +904,59 @@ impl BadamSat {
                     card,
                 },
             ) => {
-                if (player != transition_player) || !valid_actions.contains(&action) {
-                    Err(InvalidTransition)
+                if (player != transition_player) || !valid_actions.contains(action) {
+                    match self.playing_area.check_play(*card) {
+                        Ok(_) => Err(InvalidTransition::NotAllowed),
+                        Err(reason) => Err(reason.into()),
+                    }
                 } else {
-                    self.playing_area.try_play(*card).unwrap();
-                    self.players[*player].remove_card(card);
-                    self.state = match self.find_valid_actions() {
-                        Some(valid_actions) => GameState::InPlay {
-                            player: (player + 1) % self.players.len(),
-                            valid_actions,
-                        },
-                        None => GameState::Over { winner: *player },
-                    };
+                    let player = *player;
+                    if self.playing_area.replace_joker(card) {
+                        self.players[player].add_card(Card::new_joker());
+                    } else {
+                        let idx = self.playing_area.try_play(*card).unwrap();
+                        self.credit_completion(idx, player);
+                    }
+                    self.players[player].remove_card(card);
+                    self.passes_without_play = 0;
+                    if self.special_cards {
+                        match card.rank().unwrap().value() {
+                            8 => {
+                                self.direction = match self.direction {
+                                    Direction::Clockwise => Direction::Counterclockwise,
+                                    Direction::Counterclockwise => Direction::Clockwise,
+                                }
+                            }
+                            6 => self.skip_next = true,
+                            _ => {}
+                        }
+                    }
+                    self.next_turn(player);
+                    Ok(())
+                }
+            }
+            (
+                GameState::InPlay {
+                    player,
+                    valid_actions,
+                },
+                Transition::PlayJoker {
+                    player: transition_player,
+                    substitute,
+                },
+            ) => {
+                if (player != transition_player) || !valid_actions.contains(action) {
+                    match self.playing_area.check_play(*substitute) {
+                        Ok(_) => Err(InvalidTransition::NotAllowed),
+                        Err(reason) => Err(reason.into()),
+                    }
+                } else {
+                    let player = *player;
+                    let idx = self.playing_area.try_play_joker(*substitute).unwrap();
+                    self.credit_completion(idx, player);
+                    self.players[player].remove_card(&Card::new_joker());
+                    self.passes_without_play = 0;
+                    self.next_turn(player);
                     Ok(())
                 }
             }
@@ -294,132 +969,472 @@ impl BadamSat {
                     player: transition_player,
                 },
             ) => {
-                if (player != transition_player) || !valid_actions.contains(&action) {
-                    Err(InvalidTransition)
+                if (player != transition_player) || !valid_actions.contains(action) {
+                    Err(InvalidTransition::NotAllowed)
                 } else {
-                    self.state = match self.find_valid_actions() {
-                        Some(valid_actions) => GameState::InPlay {
-                            player: (player + 1) % self.players.len(),
-                            valid_actions,
-                        },
-                        None => GameState::Over { winner: *player },
+                    let player = *player;
+                    let could_play = Self::has_play(valid_actions);
+                    if could_play {
+                        self.passes[player] += 1;
+                    }
+                    self.pass_turn(player, could_play);
+                    Ok(())
+                }
+            }
+            (
+                GameState::InPlay {
+                    player,
+                    valid_actions,
+                },
+                Transition::Draw {
+                    player: transition_player,
+                },
+            ) => {
+                if (player != transition_player) || !valid_actions.contains(action) {
+                    Err(InvalidTransition::NotAllowed)
+                } else {
+                    let player = *player;
+                    let card = self
+                        .stock
+                        .pop()
+                        .expect("drawing is only valid while the stock has cards");
+                    self.players[player].add_card(card);
+                    self.passes_without_play = 0;
+                    self.next_turn(player);
+                    Ok(())
+                }
+            }
+            (
+                GameState::InPlay {
+                    player,
+                    valid_actions,
+                },
+                Transition::SkipTurn {
+                    player: transition_player,
+                },
+            ) => {
+                if player != transition_player {
+                    Err(InvalidTransition::NotAllowed)
+                } else {
+                    let player = *player;
+                    let could_play = Self::has_play(valid_actions);
+                    self.pass_turn(player, could_play);
+                    Ok(())
+                }
+            }
+            (
+                GameState::InPlay { player, .. },
+                Transition::SitOut {
+                    player: transition_player,
+                },
+            ) => {
+                if player != transition_player {
+                    Err(InvalidTransition::NotAllowed)
+                } else {
+                    // the player's cards can never be played, so the turn
+                    // counts as one without a play
+                    let player = *player;
+                    self.pass_turn(player, false);
+                    Ok(())
+                }
+            }
+            (GameState::InPlay { .. }, Transition::EndGame) => {
+                self.state = GameState::Over {
+                    winner: self.early_winner(),
+                    reason: EndReason::Timeout,
+                };
+                Ok(())
+            }
+            (GameState::InPlay { .. }, Transition::Forfeit { winner }) => {
+                if *winner >= self.players.len() {
+                    Err(InvalidTransition::NotAllowed)
+                } else {
+                    self.state = GameState::Over {
+                        winner: *winner,
+                        reason: EndReason::AllForfeited,
                     };
                     Ok(())
                 }
             }
-            (GameState::Over { .. }, _) => Err(InvalidTransition),
+            (GameState::Over { .. }, _) => Err(InvalidTransition::NotAllowed),
         }
     }
 
-    /// Retrieve the winner of the game, if any.
+    fn has_play(valid_actions: &HashSet<Transition>) -> bool {
+        valid_actions.iter().any(|action| {
+            matches!(
+                action,
+                Transition::Play { .. } | Transition::PlayJoker { .. }
+            )
+        })
+    }
+
+    /// Move the turn on from `player`, who passed or was skipped.
+    ///
+    /// Once every player still holding cards has passed in a row without
+    /// being able to play, nobody holds a card that could continue the game,
+    /// so it ends in a stalemate as if [`Transition::EndGame`] was applied.
+    fn pass_turn(&mut self, player: usize, could_play: bool) {
+        if could_play {
+            self.passes_without_play = 0;
+        } else {
+            self.passes_without_play += 1;
+        }
+        self.next_turn(player);
+        let players_left = self
+            .players
+            .iter()
+            .filter(|player| player.hand_len() > 0)
+            .count();
+        if matches!(self.state, GameState::InPlay { .. })
+            && self.passes_without_play >= players_left
+        {
+            self.state = GameState::Over {
+                winner: self.early_winner(),
+                reason: EndReason::Stalemate,
+            };
+        }
+    }
+
+    /// Find the winner of a game that ended before it was played out: the
+    /// first player out of cards, or else the one with the fewest cards left.
+    fn early_winner(&self) -> usize {
+        self.finished.first().copied().unwrap_or_else(|| {
+            (0..self.players.len())
+                .min_by_key(|player| self.players[*player].hand_len())
+                .unwrap()
+        })
+    }
+
+    /// End the game early, for example when it runs out of time. The first
+    /// player out of cards wins, or else the player with the fewest cards
+    /// left, ties going to the earlier seat.
+    pub fn end_game(&mut self) -> Result<(), InvalidTransition> {
+        self.update(Transition::EndGame)
+    }
+
+    /// End the game because every player but the `winner` gave up.
+    pub fn forfeit(&mut self, winner: usize) -> Result<(), InvalidTransition> {
+        self.update(Transition::Forfeit { winner })
+    }
+
+    /// Restart the game in place with the same configuration.
+    ///
+    /// The playing area is cleared, every hand is emptied and the history is
+    /// forgotten, bringing the game back to before the deal. The next
+    /// [`Transition::DealCards`] reshuffles and deals the cards again. A
+    /// seeded game draws its next seed from its current one, so it deals new
+    /// hands that can still be reproduced with [`BadamSat::config`].
+    pub fn restart(&mut self) {
+        if let Some(seed) = self.seed {
+            self.seed = Some(StdRng::from_seed(seed).gen());
+        }
+        self.playing_area.reset();
+        for player in self.players.iter_mut() {
+            player.clear_hand();
+        }
+        self.history.clear();
+        self.deal_order.clear();
+        self.stock.clear();
+        self.completion_credits.clear();
+        self.direction = Direction::Clockwise;
+        self.skip_next = false;
+        self.finished.clear();
+        self.passes_without_play = 0;
+        self.passes.fill(0);
+        self.state = GameState::PrePlay;
+    }
+
+    /// Skip the current player's turn regardless of their valid actions, for
+    /// example when they run out of time.
+    pub fn skip_turn(&mut self) -> Result<(), InvalidTransition> {
+        match self.state {
+            GameState::InPlay { player, .. } => self.update(Transition::SkipTurn { player }),
+            _ => Err(InvalidTransition::NotAllowed),
+        }
+    }
+
+    /// Pass over the current player's turn because they can no longer act,
+    /// for example because they left the game.
+    ///
+    /// Unlike [`BadamSat::skip_turn`], the turn counts as one in which nothing
+    /// could be played even if they hold a playable card, so that the game
+    /// still ends in a stalemate once the others are stuck.
+    pub fn sit_out(&mut self) -> Result<(), InvalidTransition> {
+        match self.state {
+            GameState::InPlay { player, .. } => self.update(Transition::SitOut { player }),
+            _ => Err(InvalidTransition::NotAllowed),
+        }
+    }
+
+    /// Move the turn on from `player`, who just finished their turn, to the
+    /// next player, or end the game if nobody else needs to play.
+    fn next_turn(&mut self, player: usize) {
+        if self.players[player].hand_len() == 0 {
+            self.finished.push(player);
+        }
+        self.state = match self.find_valid_actions() {
+            Some(valid_actions) => GameState::InPlay {
+                player: self.next_player(player),
+                valid_actions,
+            },
+            None => GameState::Over {
+                winner: self.finished.first().copied().unwrap_or(player),
+                reason: EndReason::PlayerEmptied,
+            },
+        };
+        self.skip_next = false;
+    }
+
+    /// Check whether the game ends after the `player`'s turn.
+    fn is_finished_after(&self, player: usize) -> bool {
+        if self.play_until_last {
+            self.players
+                .iter()
+                .filter(|player| player.hand_len() > 0)
+                .count()
+                <= 1
+        } else {
+            self.players[player].hand_len() == 0
+        }
+    }
+
+    /// Get the player who plays after `player`, following the direction of
+    /// the turns and any pending skip. Players without cards are passed over.
+    fn next_player(&self, player: usize) -> usize {
+        let count = self.players.len();
+        let mut steps = if self.skip_next { 2 } else { 1 };
+        let mut next = player;
+        while steps > 0 {
+            next = match self.direction {
+                Direction::Clockwise => (next + 1) % count,
+                Direction::Counterclockwise => (next + count - 1) % count,
+            };
+            if self.players[next].hand_len() > 0 {
+                steps -= 1;
+            }
+        }
+        next
+    }
+
+    /// Get the direction the turns currently go around the table.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Get the player whose turn it is, if the game is in play.
+    pub fn current_player(&self) -> Option<usize> {
+        match self.state {
+            GameState::InPlay { player, .. } => Some(player),
+            _ => None,
+        }
+    }
+
+    /// Get the number of cards dealt to each player at the start of the game.
+    pub fn starting_hand_sizes(&self) -> &[usize] {
+        &self.hand_sizes
+    }
+
+    /// Get the number of cards left in the stock to draw from.
+    pub fn stock_len(&self) -> usize {
+        self.stock.len()
+    }
+
+    /// Get the number of times each player has passed so far while they had
+    /// a card they could play.
+    pub fn pass_counts(&self) -> &[usize] {
+        &self.passes
+    }
+
+    /// Get all the [`Transition`]s applied to the game so far, in order.
+    pub fn history(&self) -> &[Transition] {
+        &self.history
+    }
+
+    /// Get the players who ran out of cards so far, in the order they did.
+    pub fn out_of_cards(&self) -> &[usize] {
+        &self.finished
+    }
+
+    /// Get the order in which the players finished, once the game is over.
+    ///
+    /// The winner comes first, even if they won by forfeit. Then come the
+    /// players who ran out of cards, in the order they did, and then the
+    /// others in order of fewest cards left in hand. Players with as many
+    /// cards left are ordered by seat, the earlier seat first, so the order
+    /// does not depend on how the game ended.
+    pub fn finishing_order(&self) -> Option<Vec<usize>> {
+        let winner = self.winner()?;
+        let mut order = vec![winner];
+        order.extend(self.finished.iter().filter(|player| **player != winner));
+        let mut rest: Vec<usize> = (0..self.players.len())
+            .filter(|player| !order.contains(player))
+            .collect();
+        rest.sort_by_key(|player| (self.players[*player].hand_len(), *player));
+        order.extend(rest);
+        Some(order)
+    }
+
+    /// Credit the `player` with completing the suit of the stack at `idx`, if
+    /// their play just completed it. Only the first completed stack of each
+    /// suit counts.
+    fn credit_completion(&mut self, idx: usize, player: usize) {
+        let stack = &self.playing_area.card_stacks[idx];
+        if stack.is_complete() {
+            self.completion_credits.entry(stack.suit).or_insert(player);
+        }
+    }
+
+    /// Get the player who completed each suit by playing its last card.
+    pub fn completion_credits(&self) -> &HashMap<Suit, usize> {
+        &self.completion_credits
+    }
+
+    /// Get the score of each player, which is the number of cards left in
+    /// their hand, less the completion bonus for every suit they completed.
+    /// Lower is better.
+    pub fn scores(&self) -> Vec<isize> {
+        let mut scores: Vec<isize> = self
+            .players
+            .iter()
+            .map(|player| player.hand_len() as isize)
+            .collect();
+        for player in self.completion_credits.values() {
+            scores[*player] -= self.completion_bonus as isize;
+        }
+        scores
+    }
+
+    /// Retrieve the winner of the game, if any.
     pub fn winner(&self) -> Option<usize> {
         match self.state {
-            GameState::Over { winner } => Some(winner),
+            GameState::Over { winner, .. } => Some(winner),
+            _ => None,
+        }
+    }
+
+    /// Get the reason the game ended, if it is over.
+    pub fn end_reason(&self) -> Option<EndReason> {
+        match self.state {
+            GameState::Over { reason, .. } => Some(reason),
             _ => None,
         }
     }
 
     /// Deal cards to the players.
     fn deal(&mut self) {
-        let mut deck = StandardDeckBuilder::new().subdecks(self.decks).build();
-        let mut rng = thread_rng();
-        deck.shuffle(&mut rng);
-        let num_cards = self.decks * 52;
-        let (cards_per_player, leftover) =
-            (num_cards / self.player_count, num_cards % self.player_count);
+        let mut cards = cards_to_deal(self.decks, self.jokers);
+        match self.seed {
+            Some(seed) => cards.shuffle(&mut StdRng::from_seed(seed)),
+            None => cards.shuffle(&mut thread_rng()),
+        }
         let mut cards_taken = 0;
-        for (idx, player) in self.players.iter_mut().enumerate() {
-            let cards_to_take = if idx < leftover {
-                cards_per_player + 1
-            } else {
-                cards_per_player
-            };
-            player.assign_cards(deck.iter().skip(cards_taken).take(cards_to_take).cloned());
+        for (seat, cards_to_take) in self.hand_sizes.iter().enumerate() {
+            let dealt = &cards[cards_taken..cards_taken + cards_to_take];
+            self.deal_order
+                .extend(dealt.iter().map(|card| (seat, *card)));
+            self.players[seat].assign_cards(dealt.iter().cloned());
             cards_taken += cards_to_take;
         }
+        self.stock = cards.split_off(cards_taken);
+        if self.autoseed_sevens {
+            for suit in Suit::all_suits() {
+                let seven = Card::new_normal(suit, Rank::new(7));
+                for _ in 0..self.decks {
+                    match self
+                        .players
+                        .iter_mut()
+                        .find(|player| player.has_card(&seven))
+                    {
+                        Some(player) => player.remove_card(&seven),
+                        None => {
+                            let idx = self
+                                .stock
+                                .iter()
+                                .position(|card| card == &seven)
+                                .expect("every seven is dealt to some player or the stock");
+                            self.stock.remove(idx);
+                        }
+                    }
+                    self.playing_area.try_play(seven).unwrap();
+                }
+            }
+        }
+    }
+
+    /// Find the player holding the opening card, the 7 of hearts, who gets the
+    /// first turn.
+    fn opening_player(&self) -> usize {
+        let opening_card = Card::new_normal(Suit::Hearts, Rank::new(7));
+        self.players
+            .iter()
+            .position(|player| player.has_card(&opening_card))
+            .unwrap_or(0)
     }
 
     /// Find all valid [`Transition`]s for the current state of the game.
     fn find_valid_actions(&self) -> Option<HashSet<Transition>> {
         let player_idx = match self.state {
-            GameState::PrePlay => 0,
+            GameState::PrePlay => self.opening_player(),
             GameState::InPlay { player, .. } => {
-                if self.players[player].hand_len() == 0 {
+                if self.is_finished_after(player) {
                     return None;
                 }
-                (player + 1) % self.players.len()
+                self.next_player(player)
             }
             GameState::Over { .. } => return None,
         };
-        let valid_cards: HashSet<Card> = self
+        let valid_cards = self.playing_area.open_slots();
+        // cards standing in for jokers can be replaced by the actual cards
+        let replaceable_cards: HashSet<Card> = self
             .playing_area
             .card_stacks
             .iter()
-            .flat_map(|stack| {
-                let mut cards = HashSet::with_capacity(2);
-                match stack.stack_state {
-                    StackState::Empty => {
-                        cards.insert(Card::new_normal(stack.suit, Rank::new(7)));
-                    }
-                    StackState::SevenOnly => {
-                        cards.insert(Card::new_normal(stack.suit, Rank::new(8)));
-                        cards.insert(Card::new_normal(stack.suit, Rank::new(6)));
-                    }
-                    StackState::LowOnly(card) => {
-                        cards.insert(Card::new_normal(stack.suit, Rank::new(8)));
-                        if card.rank().unwrap().value() != 1 {
-                            cards.insert(Card::new_normal(
-                                stack.suit,
-                                Rank::new(card.rank().unwrap().value() - 1),
-                            ));
-                        }
-                    }
-                    StackState::HighOnly(card) => {
-                        cards.insert(Card::new_normal(stack.suit, Rank::new(6)));
-                        if card.rank().unwrap().value() != 13 {
-                            cards.insert(Card::new_normal(
-                                stack.suit,
-                                Rank::new(card.rank().unwrap().value() + 1),
-                            ));
-                        }
-                    }
-                    StackState::LowAndHigh { low, high } => {
-                        if low.rank().unwrap().value() != 1 {
-                            cards.insert(Card::new_normal(
-                                stack.suit,
-                                Rank::new(low.rank().unwrap().value() - 1),
-                            ));
-                        }
-                        if high.rank().unwrap().value() != 13 {
-                            cards.insert(Card::new_normal(
-                                stack.suit,
-                                Rank::new(high.rank().unwrap().value() + 1),
-                            ));
-                        }
-                    }
-                }
-                cards
-            })
+            .flat_map(|stack| stack.jokers.iter().cloned())
             .collect();
         let player_cards = self.players[player_idx].unique_cards_in_hand();
         let mut actions: HashSet<Transition> = valid_cards
-            .intersection(&player_cards)
+            .union(&replaceable_cards)
+            .filter(|card| player_cards.contains(card))
             .map(|card| Transition::Play {
                 player: player_idx,
                 card: *card,
             })
             .collect();
+        if player_cards.contains(&Card::new_joker()) {
+            actions.extend(
+                valid_cards
+                    .iter()
+                    .filter(|card| !self.wild_sevens || is_seven(card))
+                    .map(|card| Transition::PlayJoker {
+                        player: player_idx,
+                        substitute: *card,
+                    }),
+            );
+        }
         // first move must be 7 of hearts
         if self.playing_area.is_empty() {
             actions.retain(|action| match action {
-                Transition::DealCards => false,
+                Transition::DealCards
+                | Transition::SkipTurn { .. }
+                | Transition::SitOut { .. }
+                | Transition::EndGame
+                | Transition::Forfeit { .. } => false,
                 Transition::Play { card, .. } => {
                     card == &Card::new_normal(Suit::Hearts, Rank::new(7))
                 }
-                Transition::Pass { .. } => true,
+                Transition::PlayJoker { substitute, .. } => {
+                    substitute == &Card::new_normal(Suit::Hearts, Rank::new(7))
+                }
+                Transition::Pass { .. } | Transition::Draw { .. } => true,
             })
         }
-        if actions.is_empty() {
+        let may_pass = self
+            .max_passes
+            .is_none_or(|max_passes| self.passes[player_idx] < max_passes);
+        if actions.is_empty() && !self.stock.is_empty() {
+            actions.insert(Transition::Draw { player: player_idx });
+        } else if actions.is_empty() || (self.allow_voluntary_pass && may_pass) {
             actions.insert(Transition::Pass { player: player_idx });
         }
         Some(actions)
@@ -430,6 +1445,28 @@ impl BadamSat {
         &self.playing_area
     }
 
+    /// Get the cards that could be played next by whoever holds them, based
+    /// only on the playing area. This includes the cards standing in for
+    /// jokers, and only the 7 of hearts before the first move.
+    pub fn open_cards(&self) -> Vec<Card> {
+        let opening_card = Card::new_normal(Suit::Hearts, Rank::new(7));
+        if self.playing_area.is_empty() {
+            return vec![opening_card];
+        }
+        let mut cards: Vec<Card> = Vec::new();
+        for card in self.playing_area.open_slots().into_iter().chain(
+            self.playing_area
+                .card_stacks
+                .iter()
+                .flat_map(|stack| stack.jokers.iter().cloned()),
+        ) {
+            if !cards.contains(&card) {
+                cards.push(card);
+            }
+        }
+        cards
+    }
+
     /// Get the hand of the `player`.
     pub fn hand_of_player(&self, player: usize) -> Option<&[Card]> {
         self.players.get(player).map(|player| player.hand())
@@ -438,8 +1475,1218 @@ impl BadamSat {
     pub fn hand_len(&self, player: usize) -> Option<usize> {
         self.players.get(player).map(|player| player.hand_len())
     }
+
+    /// Get the seat that each card went to, in the order the cards were
+    /// dealt. The cards themselves are left out so that the hands stay
+    /// hidden.
+    pub fn deal_sequence(&self) -> Vec<usize> {
+        self.deal_order.iter().map(|(seat, _)| *seat).collect()
+    }
+
+    /// Get the cards dealt to the `player`, in the order they were dealt.
+    ///
+    /// Unlike the hand, this includes the cards the player has since played.
+    pub fn dealt_cards(&self, player: usize) -> Option<Vec<Card>> {
+        (player < self.players.len()).then(|| {
+            self.deal_order
+                .iter()
+                .filter(|(seat, _)| *seat == player)
+                .map(|(_, card)| *card)
+                .collect()
+        })
+    }
+
+    /// Get what the `player` is allowed to see of the game: the playing
+    /// area, how many cards everyone holds and their own hand.
+    pub fn view_for(&self, player: usize) -> Option<PlayerView> {
+        Some(PlayerView {
+            playing_area: self.playing_area.clone(),
+            card_counts: self.players.iter().map(Player::hand_len).collect(),
+            hand: self.players.get(player)?.hand().to_vec(),
+        })
+    }
+
+    /// Get the [`Card`]s in the players' hands that can never be played, no
+    /// matter how the game progresses.
+    ///
+    /// A card is stranded when the cards connecting it to its stacks are
+    /// missing from the game and there are not enough jokers left to stand in
+    /// for them. With complete decks every card is eventually playable. Cards
+    /// still in the stock count as available.
+    pub fn dead_cards(&self) -> Vec<Card> {
+        let held_cards: HashSet<Card> = self
+            .players
+            .iter()
+            .flat_map(|player| player.hand().iter().cloned())
+            .chain(self.stock.iter().cloned())
+            .collect();
+        let jokers = self
+            .players
+            .iter()
+            .flat_map(|player| player.hand())
+            .chain(&self.stock)
+            .filter(|card| card.suit().is_none())
+            .count();
+        self.players
+            .iter()
+            .flat_map(|player| player.hand())
+            .filter(|card| card.suit().is_some())
+            .filter(|card| {
+                !self.playing_area.card_stacks.iter().any(|stack| {
+                    stack
+                        .missing_cards_to(card, &held_cards)
+                        .is_some_and(|missing| {
+                            missing.len() <= jokers
+                                && (!self.wild_sevens || missing.iter().all(is_seven))
+                        })
+                })
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Get the [`Card`]s that the `player` can legally play this turn.
+    ///
+    /// Returns an empty `Vec` if it is not the `player`'s turn.
+    pub fn playable_cards(&self, player: usize) -> Vec<Card> {
+        match &self.state {
+            GameState::InPlay {
+                player: current_player,
+                valid_actions,
+            } if *current_player == player => valid_actions
+                .iter()
+                .filter_map(|action| match action {
+                    Transition::Play { card, .. } => Some(*card),
+                    Transition::PlayJoker { .. } => Some(Card::new_joker()),
+                    _ => None,
+                })
+                .collect::<HashSet<Card>>()
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Check whether the `player` may pass this turn, either because they have
+    /// nothing to play or because voluntary passes are allowed.
+    ///
+    /// Returns `false` if it is not the `player`'s turn.
+    pub fn can_pass(&self, player: usize) -> bool {
+        match &self.state {
+            GameState::InPlay {
+                player: current_player,
+                valid_actions,
+            } if *current_player == player => valid_actions.contains(&Transition::Pass { player }),
+            _ => false,
+        }
+    }
+
+    /// Suggest a card for the `player` to play this turn, favouring the cards
+    /// that grow the longest stacks so that runs get completed.
+    ///
+    /// This is a simple heuristic meant for beginners, not the best move.
+    /// Jokers are only suggested when nothing else can be played. Returns
+    /// `None` if the `player` can only pass.
+    pub fn hint(&self, player: usize) -> Option<Card> {
+        self.playable_cards(player).into_iter().max_by_key(|card| {
+            let stack_len = self
+                .playing_area
+                .placement_for(*card)
+                .map_or(0, |idx| self.playing_area.card_stacks[idx].len());
+            let suit = card
+                .suit()
+                .and_then(|suit| Suit::all_suits().iter().position(|s| s == suit));
+            let rank = card.rank().map(|rank| rank.value());
+            (rank.is_some(), stack_len, suit, rank)
+        })
+    }
+}
+
+/// The parts of a [`BadamSat`] game visible to one player.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PlayerView {
+    playing_area: PlayingArea,
+    card_counts: Vec<usize>,
+    hand: Vec<Card>,
+}
+
+impl PlayerView {
+    /// Get the played cards.
+    pub fn playing_area(&self) -> &PlayingArea {
+        &self.playing_area
+    }
+
+    /// Get the number of cards in each player's hand.
+    pub fn card_counts(&self) -> &[usize] {
+        &self.card_counts
+    }
+
+    /// Get the cards in the viewing player's hand.
+    pub fn hand(&self) -> &[Card] {
+        &self.hand
+    }
+}
+
+/// Reasons why a [`Transition`] was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InvalidTransition {
+    #[error("attempted transition is not valid for the current game state")]
+    NotAllowed,
+    #[error(transparent)]
+    InvalidPlay(#[from] InvalidPlay),
 }
 
 #[derive(Debug, thiserror::Error)]
-#[error("attempted transition is not valid for the current game state")]
-pub struct InvalidTransition;
+#[error("move {index} of the replay is invalid: {reason}")]
+pub struct InvalidReplay {
+    pub index: usize,
+    pub reason: InvalidTransition,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("hands do not make up exactly the cards of the given number of decks")]
+pub struct InvalidHands;
+
+fn is_seven(card: &Card) -> bool {
+    card.rank().is_some_and(|rank| rank.value() == 7)
+}
+
+/// Get the cards of `decks` number of standard decks along with `jokers`
+/// number of jokers, unshuffled.
+fn cards_to_deal(decks: usize, jokers: usize) -> Vec<Card> {
+    let deck = StandardDeckBuilder::new().subdecks(decks).build();
+    deck.iter()
+        .cloned()
+        .chain(iter::repeat_n(Card::new_joker(), jokers))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BadamSat, BadamSatBuilder, CardStack, Direction, EndReason, InvalidPlay, InvalidTransition,
+        LeftoverPolicy, PlayingArea, StackState, Transition,
+    };
+    use crate::players::Player;
+    use card_deck::standard_deck::{Card, Rank, StandardDeckBuilder, Suit};
+    use std::{
+        collections::HashSet,
+        sync::{Arc, Mutex},
+    };
+
+    /// Split a single deck between two players, the first one getting the
+    /// hearts and the clubs.
+    fn two_player_hands() -> Vec<Vec<Card>> {
+        let (first, second): (Vec<Card>, Vec<Card>) = Suit::all_suits()
+            .into_iter()
+            .flat_map(|suit| (1..=13).map(move |rank| Card::new_normal(suit, Rank::new(rank))))
+            .partition(|card| matches!(card.suit().unwrap(), Suit::Hearts | Suit::Clubs));
+        vec![first, second]
+    }
+
+    #[test]
+    fn test_playable_cards_only_on_own_turn() {
+        let mut game = BadamSat::with_player_and_deck_capacity(4, 1);
+        assert!(game.playable_cards(0).is_empty());
+        game.update(Transition::DealCards).unwrap();
+        let opening_card = Card::new_normal(Suit::Hearts, Rank::new(7));
+        for player in 0..4 {
+            if game.hand_of_player(player).unwrap().contains(&opening_card) {
+                assert_eq!(game.playable_cards(player), vec![opening_card]);
+            } else {
+                assert!(game.playable_cards(player).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_try_play_reports_rank_mismatch() {
+        let mut playing_area = PlayingArea::with_deck_capacity(1);
+        assert_eq!(
+            playing_area.try_play(Card::new_normal(Suit::Hearts, Rank::new(8))),
+            Err(InvalidPlay::RankMismatch)
+        );
+        playing_area
+            .try_play(Card::new_normal(Suit::Hearts, Rank::new(7)))
+            .unwrap();
+        assert_eq!(
+            playing_area.try_play(Card::new_normal(Suit::Hearts, Rank::new(10))),
+            Err(InvalidPlay::RankMismatch)
+        );
+    }
+
+    #[test]
+    fn test_joker_played_and_replaced() {
+        let mut game = BadamSatBuilder::new(2, 1).jokers(1).build();
+        game.start_with_hands(vec![
+            vec![
+                Card::new_normal(Suit::Hearts, Rank::new(7)),
+                Card::new_joker(),
+                Card::new_normal(Suit::Clubs, Rank::King),
+            ],
+            vec![
+                Card::new_normal(Suit::Hearts, Rank::new(6)),
+                Card::new_normal(Suit::Hearts, Rank::new(8)),
+                Card::new_normal(Suit::Clubs, Rank::new(2)),
+            ],
+        ]);
+        game.update(Transition::Play {
+            player: 0,
+            card: Card::new_normal(Suit::Hearts, Rank::new(7)),
+        })
+        .unwrap();
+        game.update(Transition::Play {
+            player: 1,
+            card: Card::new_normal(Suit::Hearts, Rank::new(6)),
+        })
+        .unwrap();
+        game.update(Transition::PlayJoker {
+            player: 0,
+            substitute: Card::new_normal(Suit::Hearts, Rank::new(8)),
+        })
+        .unwrap();
+        let hearts = |game: &BadamSat| {
+            game.playing_area
+                .stacks()
+                .iter()
+                .find(|stack| stack.suit() == &Suit::Hearts)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(
+            hearts(&game).jokers(),
+            &[Card::new_normal(Suit::Hearts, Rank::new(8))]
+        );
+        assert!(!game.players[0].has_card(&Card::new_joker()));
+
+        game.update(Transition::Play {
+            player: 1,
+            card: Card::new_normal(Suit::Hearts, Rank::new(8)),
+        })
+        .unwrap();
+        assert!(hearts(&game).jokers().is_empty());
+        assert!(game.players[1].has_card(&Card::new_joker()));
+        assert_eq!(game.hand_len(1), Some(2));
+    }
+
+    #[test]
+    fn test_wild_sevens() {
+        let mut game = BadamSatBuilder::new(2, 1)
+            .jokers(1)
+            .wild_sevens(true)
+            .build();
+        game.start_with_hands(vec![
+            vec![
+                Card::new_normal(Suit::Hearts, Rank::new(7)),
+                Card::new_joker(),
+                Card::new_normal(Suit::Clubs, Rank::King),
+            ],
+            vec![
+                Card::new_normal(Suit::Hearts, Rank::new(6)),
+                Card::new_normal(Suit::Hearts, Rank::new(8)),
+                Card::new_normal(Suit::Clubs, Rank::new(2)),
+            ],
+        ]);
+        game.update(Transition::Play {
+            player: 0,
+            card: Card::new_normal(Suit::Hearts, Rank::new(7)),
+        })
+        .unwrap();
+        game.update(Transition::Play {
+            player: 1,
+            card: Card::new_normal(Suit::Hearts, Rank::new(6)),
+        })
+        .unwrap();
+        assert_eq!(
+            game.update(Transition::PlayJoker {
+                player: 0,
+                substitute: Card::new_normal(Suit::Hearts, Rank::new(8)),
+            }),
+            Err(InvalidTransition::NotAllowed)
+        );
+        game.update(Transition::PlayJoker {
+            player: 0,
+            substitute: Card::new_normal(Suit::Clubs, Rank::new(7)),
+        })
+        .unwrap();
+        let clubs = game
+            .playing_area
+            .stacks()
+            .iter()
+            .find(|stack| stack.suit() == &Suit::Clubs)
+            .unwrap();
+        assert_eq!(clubs.stack_state(), &StackState::SevenOnly);
+        assert_eq!(
+            clubs.jokers(),
+            &[Card::new_normal(Suit::Clubs, Rank::new(7))]
+        );
+        assert_eq!(
+            game.config(),
+            BadamSatBuilder::new(2, 1).jokers(1).wild_sevens(true)
+        );
+    }
+
+    #[test]
+    fn test_draw_pile() {
+        let mut game = BadamSatBuilder::new(4, 1)
+            .hand_size(Some(5))
+            .seed([3; 32])
+            .build();
+        game.update(Transition::DealCards).unwrap();
+        assert!((0..4).all(|player| game.hand_len(player) == Some(5)));
+        assert_eq!(game.stock_len(), 32);
+
+        let mut draws = 0;
+        while let Some(player) = game.current_player() {
+            let action = match game.playable_cards(player).first() {
+                Some(card) => Transition::Play {
+                    player,
+                    card: *card,
+                },
+                None if game.stock_len() > 0 => {
+                    assert_eq!(
+                        game.update(Transition::Pass { player }),
+                        Err(InvalidTransition::NotAllowed)
+                    );
+                    draws += 1;
+                    Transition::Draw { player }
+                }
+                None => Transition::Pass { player },
+            };
+            let hand_len = game.hand_len(player).unwrap();
+            let stock_len = game.stock_len();
+            game.update(action.clone()).unwrap();
+            if let Transition::Draw { .. } = action {
+                assert_eq!(game.hand_len(player), Some(hand_len + 1));
+                assert_eq!(game.stock_len(), stock_len - 1);
+            }
+        }
+        assert!(draws > 0);
+        let replayed = BadamSat::replay(game.config(), game.history()).unwrap();
+        assert_eq!(replayed.winner(), game.winner());
+        assert_eq!(replayed.stock_len(), game.stock_len());
+    }
+
+    #[test]
+    fn test_transition_observer() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut game = BadamSat::with_player_and_deck_capacity(4, 1);
+        game.on_transition(Box::new({
+            let seen = Arc::clone(&seen);
+            move |transition, game| {
+                seen.lock()
+                    .unwrap()
+                    .push((transition.clone(), game.current_player()));
+            }
+        }));
+        game.update(Transition::DealCards).unwrap();
+        let player = game.current_player().unwrap();
+        assert!(game.update(Transition::Pass { player }).is_err());
+        game.update(Transition::Play {
+            player,
+            card: Card::new_normal(Suit::Hearts, Rank::new(7)),
+        })
+        .unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_eq!(seen[0], (Transition::DealCards, Some(player)));
+        assert_eq!(seen[1].0, game.history()[1]);
+        assert_eq!(seen[1].1, game.current_player());
+    }
+
+    #[test]
+    fn test_from_hands() {
+        let game = BadamSat::from_hands(two_player_hands(), 1).unwrap();
+        assert_eq!(
+            game.playable_cards(0),
+            vec![Card::new_normal(Suit::Hearts, Rank::new(7))]
+        );
+        assert!(game.playable_cards(1).is_empty());
+    }
+
+    #[test]
+    fn test_from_hands_rejects_incomplete_deck() {
+        let mut hands = two_player_hands();
+        hands[1].pop();
+        assert!(BadamSat::from_hands(hands, 1).is_err());
+        assert!(BadamSat::from_hands(two_player_hands(), 2).is_err());
+    }
+
+    #[test]
+    fn test_opener_moves_first() {
+        let mut hands = two_player_hands();
+        hands.reverse();
+        let mut game = BadamSat::from_hands(hands, 1).unwrap();
+        assert!(game.playable_cards(0).is_empty());
+        game.update(Transition::Play {
+            player: 1,
+            card: Card::new_normal(Suit::Hearts, Rank::new(7)),
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_dead_cards() {
+        let mut game = BadamSat::from_hands(two_player_hands(), 1).unwrap();
+        assert!(game.dead_cards().is_empty());
+        game.players[1].remove_card(&Card::new_normal(Suit::Spades, Rank::new(8)));
+        let mut dead_ranks: Vec<_> = game
+            .dead_cards()
+            .iter()
+            .map(|card| {
+                assert_eq!(card.suit(), Some(&Suit::Spades));
+                card.rank().unwrap().value()
+            })
+            .collect();
+        dead_ranks.sort();
+        assert_eq!(dead_ranks, vec![9, 10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn test_play_removes_card_from_hand() {
+        let mut game = BadamSat::from_hands(two_player_hands(), 1).unwrap();
+        let opening_card = Card::new_normal(Suit::Hearts, Rank::new(7));
+        game.update(Transition::Play {
+            player: 0,
+            card: opening_card,
+        })
+        .unwrap();
+        assert_eq!(game.hand_len(0), Some(25));
+        assert!(!game.hand_of_player(0).unwrap().contains(&opening_card));
+    }
+
+    #[test]
+    fn test_replay_round_trip() {
+        let mut game = BadamSatBuilder::new(4, 1).seed([7; 32]).build();
+        game.update(Transition::DealCards).unwrap();
+        while let Some(player) = game.current_player() {
+            let action = match game.playable_cards(player).first() {
+                Some(card) => Transition::Play {
+                    player,
+                    card: *card,
+                },
+                None => Transition::Pass { player },
+            };
+            game.update(action).unwrap();
+        }
+        let replayed = BadamSat::replay(game.config(), game.history()).unwrap();
+        assert_eq!(replayed.playing_area(), game.playing_area());
+        assert_eq!(replayed.winner(), game.winner());
+        for player in 0..4 {
+            assert_eq!(replayed.hand_of_player(player), game.hand_of_player(player));
+        }
+        assert_eq!(game.history().first(), Some(&Transition::DealCards));
+        assert_eq!(replayed.starting_hand_sizes(), &[13, 13, 13, 13]);
+
+        let mut moves = game.history().to_vec();
+        moves.swap(1, 2);
+        assert_eq!(
+            BadamSat::replay(game.config(), &moves).unwrap_err().index,
+            1
+        );
+    }
+
+    /// Build a spades stack by adding the `ranks` in order.
+    fn spades_stack(ranks: &[u8]) -> CardStack {
+        ranks
+            .iter()
+            .fold(CardStack::new(Suit::Spades), |stack, &rank| {
+                stack
+                    .add(Card::new_normal(Suit::Spades, Rank::new(rank)))
+                    .unwrap()
+            })
+    }
+
+    #[test]
+    fn test_card_stack_empty_accepts_only_seven() {
+        let stack = CardStack::new(Suit::Spades);
+        for rank in (1..=13).filter(|&rank| rank != 7) {
+            assert_eq!(
+                stack.add(Card::new_normal(Suit::Spades, Rank::new(rank))),
+                Err(InvalidPlay::RankMismatch)
+            );
+        }
+        assert_eq!(
+            stack
+                .add(Card::new_normal(Suit::Spades, Rank::new(7)))
+                .unwrap()
+                .stack_state(),
+            &StackState::SevenOnly
+        );
+    }
+
+    #[test]
+    fn test_card_stack_seven_only_accepts_six_or_eight() {
+        let stack = spades_stack(&[7]);
+        assert_eq!(
+            stack.add(Card::new_normal(Suit::Spades, Rank::new(5))),
+            Err(InvalidPlay::RankMismatch)
+        );
+        assert_eq!(
+            stack.add(Card::new_normal(Suit::Spades, Rank::new(9))),
+            Err(InvalidPlay::RankMismatch)
+        );
+        let six = Card::new_normal(Suit::Spades, Rank::new(6));
+        assert_eq!(
+            stack.add(six).unwrap().stack_state(),
+            &StackState::LowOnly(six)
+        );
+        let eight = Card::new_normal(Suit::Spades, Rank::new(8));
+        assert_eq!(
+            stack.add(eight).unwrap().stack_state(),
+            &StackState::HighOnly(eight)
+        );
+    }
+
+    #[test]
+    fn test_card_stack_grows_in_both_directions() {
+        let low = Card::new_normal(Suit::Spades, Rank::new(5));
+        let stack = spades_stack(&[7, 6, 5]);
+        assert_eq!(stack.stack_state(), &StackState::LowOnly(low));
+        // skipping a rank is not allowed on either side
+        assert_eq!(
+            stack.add(Card::new_normal(Suit::Spades, Rank::new(3))),
+            Err(InvalidPlay::RankMismatch)
+        );
+        assert_eq!(
+            stack.add(Card::new_normal(Suit::Spades, Rank::new(9))),
+            Err(InvalidPlay::RankMismatch)
+        );
+
+        let high = Card::new_normal(Suit::Spades, Rank::new(9));
+        let stack = spades_stack(&[7, 6, 5, 8, 9]);
+        assert_eq!(stack.stack_state(), &StackState::LowAndHigh { low, high });
+
+        let stack = spades_stack(&[7, 8, 9, 6, 5]);
+        assert_eq!(stack.stack_state(), &StackState::LowAndHigh { low, high });
+    }
+
+    #[test]
+    fn test_card_stack_full_at_ace_and_king() {
+        let ace = Card::new_normal(Suit::Spades, Rank::Ace);
+        let king = Card::new_normal(Suit::Spades, Rank::King);
+        let stack = spades_stack(&[7, 6, 5, 4, 3, 2, 1]);
+        assert_eq!(stack.stack_state(), &StackState::LowOnly(ace));
+        // an ace low stack can still grow upwards
+        assert!(stack
+            .add(Card::new_normal(Suit::Spades, Rank::new(8)))
+            .is_ok());
+
+        let stack = spades_stack(&[7, 8, 9, 10, 11, 12, 13]);
+        assert_eq!(stack.stack_state(), &StackState::HighOnly(king));
+
+        let stack = spades_stack(&[7, 6, 5, 4, 3, 2, 1, 8, 9, 10, 11, 12, 13]);
+        assert_eq!(
+            stack.stack_state(),
+            &StackState::LowAndHigh {
+                low: ace,
+                high: king
+            }
+        );
+        for rank in 1..=13 {
+            assert_eq!(
+                stack.add(Card::new_normal(Suit::Spades, Rank::new(rank))),
+                Err(InvalidPlay::StackFull)
+            );
+        }
+    }
+
+    #[test]
+    fn test_card_stack_rejects_other_suits() {
+        for stack in [
+            CardStack::new(Suit::Spades),
+            spades_stack(&[7]),
+            spades_stack(&[7, 6]),
+            spades_stack(&[7, 8]),
+            spades_stack(&[7, 6, 8]),
+        ] {
+            for card in [
+                Card::new_normal(Suit::Hearts, Rank::new(7)),
+                Card::new_normal(Suit::Clubs, Rank::new(6)),
+                Card::new_normal(Suit::Diamonds, Rank::new(9)),
+                Card::new_joker(),
+            ] {
+                assert_eq!(stack.add(card), Err(InvalidPlay::SuitMismatch));
+            }
+        }
+    }
+
+    #[test]
+    fn test_opening_card_required() {
+        assert!(BadamSatBuilder::new(4, 1).has_opening_card());
+        assert!(BadamSatBuilder::new(4, 2).jokers(2).has_opening_card());
+        assert!(!BadamSatBuilder::new(4, 0).jokers(2).has_opening_card());
+    }
+
+    #[test]
+    fn test_end_game_ranks_by_cards_left() {
+        let mut game = BadamSat::from_hands(two_player_hands(), 1).unwrap();
+        assert_eq!(game.current_player(), Some(0));
+        game.update(Transition::Play {
+            player: 0,
+            card: Card::new_normal(Suit::Hearts, Rank::new(7)),
+        })
+        .unwrap();
+        game.end_game().unwrap();
+        assert_eq!(game.winner(), Some(0));
+        assert_eq!(game.finishing_order(), Some(vec![0, 1]));
+        assert_eq!(game.end_game(), Err(InvalidTransition::NotAllowed));
+    }
+
+    #[test]
+    fn test_voluntary_pass() {
+        let mut game = BadamSat::with_player_and_deck_capacity(4, 1);
+        game.update(Transition::DealCards).unwrap();
+        let player = game.current_player().unwrap();
+        assert!(!game.can_pass(player));
+        assert_eq!(
+            game.update(Transition::Pass { player }),
+            Err(InvalidTransition::NotAllowed)
+        );
+
+        let mut game = BadamSatBuilder::new(4, 1)
+            .allow_voluntary_pass(true)
+            .seed([7; 32])
+            .build();
+        game.update(Transition::DealCards).unwrap();
+        let player = game.current_player().unwrap();
+        assert!(game.can_pass(player));
+        assert!(!game.can_pass((player + 1) % 4));
+        game.update(Transition::Pass { player }).unwrap();
+        assert_eq!(game.current_player(), Some((player + 1) % 4));
+        assert_eq!(game.history().last(), Some(&Transition::Pass { player }));
+        let replayed = BadamSat::replay(game.config(), game.history()).unwrap();
+        assert_eq!(replayed.current_player(), game.current_player());
+    }
+
+    #[test]
+    fn test_pass_limit() {
+        let mut game = BadamSatBuilder::new(1, 1)
+            .allow_voluntary_pass(true)
+            .max_passes(Some(2))
+            .seed([7; 32])
+            .build();
+        game.update(Transition::DealCards).unwrap();
+        game.update(Transition::Pass { player: 0 }).unwrap();
+        game.update(Transition::Pass { player: 0 }).unwrap();
+        assert_eq!(game.pass_counts(), &[2]);
+        assert_eq!(
+            game.update(Transition::Pass { player: 0 }),
+            Err(InvalidTransition::NotAllowed)
+        );
+        assert_eq!(game.pass_counts(), &[2]);
+
+        game.restart();
+        assert_eq!(game.pass_counts(), &[0]);
+
+        // passing with nothing to play does not count towards the limit
+        let hearts = |rank| Card::new_normal(Suit::Hearts, Rank::new(rank));
+        let mut game = BadamSatBuilder::new(2, 1)
+            .allow_voluntary_pass(true)
+            .max_passes(Some(1))
+            .build();
+        game.start_with_hands(vec![
+            vec![hearts(7), hearts(8), hearts(10)],
+            vec![Card::new_normal(Suit::Clubs, Rank::new(2)), hearts(9)],
+        ]);
+        game.update(Transition::Play {
+            player: 0,
+            card: hearts(7),
+        })
+        .unwrap();
+        game.update(Transition::Pass { player: 1 }).unwrap();
+        assert_eq!(game.pass_counts(), &[0, 0]);
+        game.update(Transition::Play {
+            player: 0,
+            card: hearts(8),
+        })
+        .unwrap();
+        game.update(Transition::Pass { player: 1 }).unwrap();
+        assert_eq!(game.pass_counts(), &[0, 1]);
+        game.update(Transition::Pass { player: 0 }).unwrap();
+        assert_eq!(
+            game.update(Transition::Pass { player: 1 }),
+            Err(InvalidTransition::NotAllowed)
+        );
+    }
+
+    #[test]
+    fn test_placement_for_matches_play() {
+        let mut playing_area = PlayingArea::with_deck_capacity(2);
+        let seven = Card::new_normal(Suit::Spades, Rank::new(7));
+        let idx = playing_area.placement_for(seven).unwrap();
+        assert_eq!(playing_area.stacks()[idx].suit(), &Suit::Spades);
+        assert_eq!(
+            playing_area.placement_for(Card::new_normal(Suit::Spades, Rank::new(6))),
+            None
+        );
+        playing_area.try_play(seven).unwrap();
+        let six = Card::new_normal(Suit::Spades, Rank::new(6));
+        assert_eq!(playing_area.placement_for(six), Some(idx));
+        // the second seven goes on the other spades stack
+        let other = playing_area.placement_for(seven).unwrap();
+        assert_ne!(other, idx);
+        assert_eq!(playing_area.stacks()[other].suit(), &Suit::Spades);
+    }
+
+    #[test]
+    fn test_restart() {
+        let mut game = BadamSat::from_hands(two_player_hands(), 1).unwrap();
+        game.update(Transition::Play {
+            player: 0,
+            card: Card::new_normal(Suit::Hearts, Rank::new(7)),
+        })
+        .unwrap();
+        game.restart();
+        assert!(game
+            .playing_area()
+            .stacks()
+            .iter()
+            .all(|stack| stack.stack_state() == &StackState::Empty));
+        assert!(game.history().is_empty());
+        assert_eq!(game.current_player(), None);
+        assert_eq!(game.hand_len(0), Some(0));
+
+        game.update(Transition::DealCards).unwrap();
+        assert_eq!(game.hand_len(0).unwrap() + game.hand_len(1).unwrap(), 52);
+        assert_eq!(game.current_player(), Some(game.opening_player()));
+
+        // a seeded game deals new hands, reproducible from its configuration
+        let mut game = BadamSatBuilder::new(4, 1).seed([5; 32]).build();
+        game.update(Transition::DealCards).unwrap();
+        let first_deal = game.hand_of_player(0).unwrap().to_vec();
+        game.restart();
+        game.update(Transition::DealCards).unwrap();
+        assert_ne!(game.hand_of_player(0).unwrap(), first_deal);
+        let replayed = BadamSat::replay(game.config(), game.history()).unwrap();
+        assert_eq!(replayed.hand_of_player(0), game.hand_of_player(0));
+    }
+
+    #[test]
+    fn test_deals_evenly() {
+        assert!(BadamSatBuilder::new(4, 1).deals_evenly());
+        assert!(BadamSatBuilder::new(13, 2).deals_evenly());
+        assert!(BadamSatBuilder::new(6, 1).jokers(2).deals_evenly());
+        assert!(!BadamSatBuilder::new(5, 1).deals_evenly());
+        assert!(!BadamSatBuilder::new(3, 2).deals_evenly());
+        assert!(!BadamSatBuilder::new(4, 1).jokers(1).deals_evenly());
+    }
+
+    #[test]
+    fn test_open_cards() {
+        let mut game = BadamSat::from_hands(two_player_hands(), 1).unwrap();
+        let seven = Card::new_normal(Suit::Hearts, Rank::new(7));
+        assert_eq!(game.open_cards(), vec![seven]);
+        game.update(Transition::Play {
+            player: 0,
+            card: seven,
+        })
+        .unwrap();
+        let open: HashSet<Card> = game.open_cards().into_iter().collect();
+        let expected: HashSet<Card> = [
+            Card::new_normal(Suit::Hearts, Rank::new(6)),
+            Card::new_normal(Suit::Hearts, Rank::new(8)),
+            Card::new_normal(Suit::Clubs, Rank::new(7)),
+            Card::new_normal(Suit::Diamonds, Rank::new(7)),
+            Card::new_normal(Suit::Spades, Rank::new(7)),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(open, expected);
+    }
+
+    #[test]
+    fn test_completion_credits() {
+        let mut game = BadamSatBuilder::new(2, 1).completion_bonus(5).build();
+        let spades = |rank| Card::new_normal(Suit::Spades, Rank::new(rank));
+        game.playing_area.card_stacks[3] = CardStack::new_with_stack_state(
+            Suit::Spades,
+            StackState::LowAndHigh {
+                low: spades(2),
+                high: spades(13),
+            },
+        );
+        game.start_with_hands(vec![
+            vec![Card::new_normal(Suit::Hearts, Rank::new(7)), spades(1)],
+            vec![
+                Card::new_normal(Suit::Hearts, Rank::new(8)),
+                Card::new_normal(Suit::Hearts, Rank::new(9)),
+            ],
+        ]);
+        game.update(Transition::Play {
+            player: 0,
+            card: spades(1),
+        })
+        .unwrap();
+        assert_eq!(game.completion_credits().get(&Suit::Spades), Some(&0));
+        assert_eq!(game.completion_credits().len(), 1);
+        assert_eq!(game.scores(), vec![-4, 2]);
+    }
+
+    fn dealt_hand_sizes(policy: LeftoverPolicy) -> Vec<usize> {
+        let mut game = BadamSatBuilder::new(3, 1)
+            .leftover_policy(policy)
+            .seed([3; 32])
+            .build();
+        game.update(Transition::DealCards).unwrap();
+        let sizes: Vec<usize> = game.players.iter().map(Player::hand_len).collect();
+        assert_eq!(sizes, game.hand_sizes);
+        sizes
+    }
+
+    #[test]
+    fn test_hint_grows_longest_stack() {
+        let hearts = |rank| Card::new_normal(Suit::Hearts, Rank::new(rank));
+        let seven_of_spades = Card::new_normal(Suit::Spades, Rank::new(7));
+        let deck = StandardDeckBuilder::new().build();
+        let (first, second) = deck
+            .iter()
+            .partition(|card| [hearts(7), seven_of_spades].contains(card));
+        let mut game = BadamSat::from_hands(vec![first, second], 1).unwrap();
+        game.update(Transition::Play {
+            player: 0,
+            card: hearts(7),
+        })
+        .unwrap();
+
+        // the other sevens would start empty stacks, the hearts grow one
+        assert_eq!(game.hint(1), Some(hearts(8)));
+        assert_eq!(game.hint(0), None);
+        game.update(Transition::Play {
+            player: 1,
+            card: hearts(8),
+        })
+        .unwrap();
+        assert_eq!(game.hint(0), Some(seven_of_spades));
+    }
+
+    #[test]
+    fn test_stalemate_ends_game() {
+        let hearts = |rank| Card::new_normal(Suit::Hearts, Rank::new(rank));
+        // the 8 of hearts is missing, so neither player can ever continue
+        // after the 7
+        let mut game = BadamSatBuilder::new(2, 1).build();
+        game.start_with_hands(vec![
+            vec![hearts(7), hearts(9)],
+            vec![hearts(10), Card::new_normal(Suit::Spades, Rank::new(13))],
+        ]);
+        game.update(Transition::Play {
+            player: 0,
+            card: hearts(7),
+        })
+        .unwrap();
+        game.update(Transition::Pass { player: 1 }).unwrap();
+        assert_eq!(game.winner(), None);
+        game.update(Transition::Pass { player: 0 }).unwrap();
+        assert_eq!(game.winner(), Some(0));
+        assert_eq!(game.end_reason(), Some(EndReason::Stalemate));
+        assert_eq!(game.current_player(), None);
+    }
+
+    #[test]
+    fn test_sitting_out_does_not_block_stalemate() {
+        let hearts = |rank| Card::new_normal(Suit::Hearts, Rank::new(rank));
+        // only the player sitting out holds the 8 of hearts
+        let mut game = BadamSatBuilder::new(3, 1).build();
+        game.start_with_hands(vec![
+            vec![hearts(7), hearts(9)],
+            vec![hearts(10), Card::new_normal(Suit::Spades, Rank::new(13))],
+            vec![hearts(8)],
+        ]);
+        game.update(Transition::Play {
+            player: 0,
+            card: hearts(7),
+        })
+        .unwrap();
+        game.update(Transition::Pass { player: 1 }).unwrap();
+        game.sit_out().unwrap();
+        assert_eq!(
+            game.history().last(),
+            Some(&Transition::SitOut { player: 2 })
+        );
+        game.update(Transition::Pass { player: 0 }).unwrap();
+        assert_eq!(game.end_reason(), Some(EndReason::Stalemate));
+    }
+
+    #[test]
+    fn test_end_reasons() {
+        let deck = StandardDeckBuilder::new().build();
+        let mut game = BadamSat::from_hands(vec![deck.iter().cloned().collect()], 1).unwrap();
+        assert_eq!(game.end_reason(), None);
+        while let Some(card) = game.hint(0) {
+            game.update(Transition::Play { player: 0, card }).unwrap();
+        }
+        assert_eq!(game.winner(), Some(0));
+        assert_eq!(game.end_reason(), Some(EndReason::PlayerEmptied));
+
+        let mut game = BadamSat::with_player_and_deck_capacity(4, 1);
+        game.update(Transition::DealCards).unwrap();
+        game.end_game().unwrap();
+        assert_eq!(game.end_reason(), Some(EndReason::Timeout));
+
+        let mut game = BadamSatBuilder::new(4, 1).seed([3; 32]).build();
+        game.update(Transition::DealCards).unwrap();
+        assert_eq!(game.forfeit(4), Err(InvalidTransition::NotAllowed));
+        game.forfeit(2).unwrap();
+        assert_eq!(game.winner(), Some(2));
+        assert_eq!(game.end_reason(), Some(EndReason::AllForfeited));
+        assert_eq!(
+            game.history().last(),
+            Some(&Transition::Forfeit { winner: 2 })
+        );
+        let replayed = BadamSat::replay(game.config(), game.history()).unwrap();
+        assert_eq!(replayed.winner(), Some(2));
+        assert_eq!(replayed.end_reason(), Some(EndReason::AllForfeited));
+    }
+
+    #[test]
+    fn test_forfeit_ties_go_to_earlier_seat() {
+        let mut game = BadamSat::with_player_and_deck_capacity(4, 1);
+        game.update(Transition::DealCards).unwrap();
+        game.forfeit(2).unwrap();
+        // everyone still holds 13 cards
+        assert_eq!(game.finishing_order(), Some(vec![2, 0, 1, 3]));
+
+        let hearts = |rank| Card::new_normal(Suit::Hearts, Rank::new(rank));
+        let mut game = BadamSatBuilder::new(3, 1).play_until_last(true).build();
+        game.start_with_hands(vec![
+            vec![hearts(7)],
+            vec![hearts(8), hearts(10)],
+            vec![hearts(6), hearts(9)],
+        ]);
+        game.update(Transition::Play {
+            player: 0,
+            card: hearts(7),
+        })
+        .unwrap();
+        game.forfeit(2).unwrap();
+        // the forfeit winner comes before the player who ran out of cards
+        assert_eq!(game.finishing_order(), Some(vec![2, 0, 1]));
+    }
+
+    #[test]
+    fn test_playing_area_from_states() {
+        let hearts = |rank| Card::new_normal(Suit::Hearts, Rank::new(rank));
+        let deck = StandardDeckBuilder::new().build();
+        let mut game = BadamSat::from_hands(vec![deck.iter().cloned().collect()], 1).unwrap();
+        for card in [hearts(7), hearts(6), hearts(8), hearts(5)] {
+            game.update(Transition::Play { player: 0, card }).unwrap();
+        }
+        let expected = PlayingArea::from_states(
+            Suit::all_suits()
+                .into_iter()
+                .map(|suit| match suit {
+                    Suit::Hearts => (
+                        suit,
+                        StackState::LowAndHigh {
+                            low: hearts(5),
+                            high: hearts(8),
+                        },
+                    ),
+                    _ => (suit, StackState::Empty),
+                })
+                .collect(),
+        );
+        assert_eq!(game.playing_area(), &expected);
+    }
+
+    #[test]
+    fn test_deal_order() {
+        let mut game = BadamSatBuilder::new(3, 1).seed([5; 32]).build();
+        game.update(Transition::DealCards).unwrap();
+        let sequence = game.deal_sequence();
+        assert_eq!(sequence.len(), 52);
+        for player in 0..3 {
+            let dealt = game.dealt_cards(player).unwrap();
+            assert_eq!(dealt.len(), game.hand_len(player).unwrap());
+            assert_eq!(
+                sequence.iter().filter(|seat| **seat == player).count(),
+                dealt.len()
+            );
+            assert!(dealt
+                .iter()
+                .all(|card| game.hand_of_player(player).unwrap().contains(card)));
+        }
+        assert_eq!(game.dealt_cards(3), None);
+
+        game.restart();
+        assert!(game.deal_sequence().is_empty());
+    }
+
+    #[test]
+    fn test_leftover_early_seats() {
+        assert_eq!(
+            dealt_hand_sizes(LeftoverPolicy::EarlySeats),
+            vec![18, 17, 17]
+        );
+    }
+
+    #[test]
+    fn test_leftover_late_seats() {
+        assert_eq!(
+            dealt_hand_sizes(LeftoverPolicy::LateSeats),
+            vec![17, 17, 18]
+        );
+    }
+
+    #[test]
+    fn test_leftover_random() {
+        let sizes = dealt_hand_sizes(LeftoverPolicy::Random);
+        assert_eq!(sizes.iter().sum::<usize>(), 52);
+        assert_eq!(sizes.iter().filter(|size| **size == 18).count(), 1);
+        assert_eq!(sizes, dealt_hand_sizes(LeftoverPolicy::Random));
+    }
+
+    #[test]
+    fn test_missing_cards() {
+        let mut area = PlayingArea::with_deck_capacity(1);
+        let hearts = |rank| Card::new_normal(Suit::Hearts, Rank::new(rank));
+        for rank in [7, 6, 8, 9, 10, 11, 12] {
+            area.try_play(hearts(rank)).unwrap();
+        }
+        area.try_play_joker(hearts(13)).unwrap();
+        let missing = area.missing_cards();
+        assert_eq!(
+            missing[&Suit::Hearts],
+            vec![
+                hearts(1),
+                hearts(2),
+                hearts(3),
+                hearts(4),
+                hearts(5),
+                hearts(13)
+            ]
+        );
+        assert_eq!(missing[&Suit::Spades].len(), 13);
+        assert_eq!(missing.len(), 4);
+    }
+
+    #[test]
+    fn test_autoseed_sevens() {
+        let mut game = BadamSatBuilder::new(4, 2).autoseed_sevens(true).build();
+        game.update(Transition::DealCards).unwrap();
+        assert!(game
+            .playing_area
+            .stacks()
+            .iter()
+            .all(|stack| stack.stack_state() == &StackState::SevenOnly));
+        assert!(game.players.iter().all(|player| player
+            .hand()
+            .iter()
+            .all(|card| card.rank() != Some(&Rank::new(7)))));
+        assert_eq!(game.scores().iter().sum::<isize>(), 96);
+        assert_eq!(game.current_player(), Some(0));
+    }
+
+    fn special_cards_game(special: bool) -> BadamSat {
+        let hearts = |rank| Card::new_normal(Suit::Hearts, Rank::new(rank));
+        let clubs = |rank| Card::new_normal(Suit::Clubs, Rank::new(rank));
+        let mut game = BadamSatBuilder::new(4, 1).special_cards(special).build();
+        game.start_with_hands(vec![
+            vec![hearts(7), clubs(2)],
+            vec![hearts(8), hearts(6)],
+            vec![hearts(5), clubs(3)],
+            vec![hearts(9), clubs(4)],
+        ]);
+        game.update(Transition::Play {
+            player: 0,
+            card: hearts(7),
+        })
+        .unwrap();
+        game
+    }
+
+    #[test]
+    fn test_eight_reverses_direction() {
+        let mut game = special_cards_game(true);
+        game.update(Transition::Play {
+            player: 1,
+            card: Card::new_normal(Suit::Hearts, Rank::new(8)),
+        })
+        .unwrap();
+        assert_eq!(game.direction(), Direction::Counterclockwise);
+        assert_eq!(game.current_player(), Some(0));
+        game.update(Transition::Pass { player: 0 }).unwrap();
+        assert_eq!(game.current_player(), Some(3));
+        assert_eq!(
+            game.playable_cards(3),
+            vec![Card::new_normal(Suit::Hearts, Rank::new(9))]
+        );
+    }
+
+    #[test]
+    fn test_six_skips_next_player() {
+        let mut game = special_cards_game(true);
+        game.update(Transition::Play {
+            player: 1,
+            card: Card::new_normal(Suit::Hearts, Rank::new(6)),
+        })
+        .unwrap();
+        assert_eq!(game.direction(), Direction::Clockwise);
+        assert_eq!(game.current_player(), Some(3));
+        // player 2 could have played the 5 of hearts, player 3 can only pass
+        game.update(Transition::Pass { player: 3 }).unwrap();
+        assert_eq!(game.current_player(), Some(0));
+    }
+
+    #[test]
+    fn test_special_cards_off_by_default() {
+        for card in [8, 6] {
+            let mut game = special_cards_game(false);
+            game.update(Transition::Play {
+                player: 1,
+                card: Card::new_normal(Suit::Hearts, Rank::new(card)),
+            })
+            .unwrap();
+            assert_eq!(game.direction(), Direction::Clockwise);
+            assert_eq!(game.current_player(), Some(2));
+        }
+    }
+
+    #[test]
+    fn test_play_until_last() {
+        let hearts = |rank| Card::new_normal(Suit::Hearts, Rank::new(rank));
+        let mut game = BadamSatBuilder::new(3, 1).play_until_last(true).build();
+        game.start_with_hands(vec![
+            vec![hearts(7)],
+            vec![hearts(8), hearts(9)],
+            vec![hearts(6), Card::new_normal(Suit::Clubs, Rank::new(3))],
+        ]);
+        for (player, rank, next) in [(0, 7, 1), (1, 8, 2), (2, 6, 1)] {
+            game.update(Transition::Play {
+                player,
+                card: hearts(rank),
+            })
+            .unwrap();
+            assert_eq!(game.current_player(), Some(next));
+        }
+        game.update(Transition::Play {
+            player: 1,
+            card: hearts(9),
+        })
+        .unwrap();
+        assert_eq!(game.winner(), Some(0));
+        assert_eq!(game.finishing_order(), Some(vec![0, 1, 2]));
+    }
+
+    #[test]
+    fn test_view_shows_only_own_hand() {
+        let mut game = BadamSat::with_player_and_deck_capacity(4, 1);
+        game.update(Transition::DealCards).unwrap();
+        for player in 0..4 {
+            let view = game.view_for(player).unwrap();
+            assert_eq!(view.hand(), game.players[player].hand());
+            assert_eq!(view.card_counts(), &[13, 13, 13, 13]);
+            for other in (0..4).filter(|other| *other != player) {
+                assert!(game.players[other]
+                    .hand()
+                    .iter()
+                    .all(|card| !view.hand().contains(card)));
+            }
+        }
+        assert!(game.view_for(4).is_none());
+    }
+}
@@ -64,6 +64,18 @@ impl Player {
         }
     }
 
+    /// Remove all cards from the player's hand, keeping its allocation. The
+    /// next assignment of cards sets the player's card capacity anew.
+    pub fn clear_hand(&mut self) {
+        self.hand.clear();
+        self.max_card_count = 0;
+    }
+
+    /// Add a single `card` to the player's hand.
+    pub fn add_card(&mut self, card: Card) {
+        self.hand.push(card);
+    }
+
     /// Remove `card` from the player's hand.
     ///
     /// # Panics
@@ -0,0 +1,60 @@
+use badam_sat::games::{BadamSat, BadamSatBuilder, StackState, Transition};
+use card_deck::standard_deck::Card;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+/// Count the cards on the playing area.
+fn played_cards(game: &BadamSat) -> usize {
+    game.playing_area()
+        .stacks()
+        .iter()
+        .map(|stack| {
+            let value = |card: &Card| card.rank().unwrap().value();
+            match stack.stack_state() {
+                StackState::Empty => 0,
+                StackState::SevenOnly => 1,
+                StackState::LowOnly(low) => 8 - value(low) as usize,
+                StackState::HighOnly(high) => value(high) as usize - 6,
+                StackState::LowAndHigh { low, high } => (value(high) - value(low)) as usize + 1,
+            }
+        })
+        .sum()
+}
+
+/// Count the cards in the hands of the `players`.
+fn held_cards(game: &BadamSat, players: usize) -> usize {
+    (0..players)
+        .map(|player| game.hand_len(player).unwrap())
+        .sum()
+}
+
+#[test]
+fn test_cards_are_conserved() {
+    let mut rng = StdRng::seed_from_u64(7);
+    for _ in 0..200 {
+        let players = rng.gen_range(1..=8);
+        let decks = rng.gen_range(1..=3);
+        let mut game = BadamSatBuilder::new(players, decks).seed(rng.gen()).build();
+        game.update(Transition::DealCards).unwrap();
+        while let Some(player) = game.current_player() {
+            let mut cards = game.playable_cards(player);
+            // the order of the playable cards is not fixed, sort them to keep
+            // the game reproducible
+            cards.sort_by_key(ToString::to_string);
+            let action = match cards.choose(&mut rng) {
+                Some(card) => Transition::Play {
+                    player,
+                    card: *card,
+                },
+                None => Transition::Pass { player },
+            };
+            game.update(action).unwrap();
+            assert_eq!(
+                held_cards(&game, players) + played_cards(&game),
+                decks * 52,
+                "cards went missing with {players} players and {decks} decks after {:?}",
+                game.history().last().unwrap(),
+            );
+        }
+        assert!(game.winner().is_some());
+    }
+}